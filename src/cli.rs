@@ -0,0 +1,427 @@
+use crate::game::clock::TimeControl;
+use crate::game::engine::Strategy;
+use crate::game::rating::System;
+use crate::game::series::{Opening, TieBreak};
+use crate::game::tournament::Format;
+use crate::game::variant::Variant;
+use crate::game::{Rules, Shape};
+
+const USAGE: &str = "Usage: tictactoe [options]
+       tictactoe stats <name>
+       tictactoe leaderboard
+       tictactoe ratings
+       tictactoe history <name>
+       tictactoe analyse <file>
+       tictactoe evaluate <position>     a position like x.o/.x./... with an optional x or o to move
+       tictactoe tablebase [--output <file>]   counts the solved positions or writes the table
+       tictactoe bench [--board <board>] [--threads <threads>]   times win checks, move generation and search
+       tictactoe tournament --engines <strategy>,<strategy>,.. [tournament options]
+
+    --time <control>       sudden:<secs> | fischer:<secs>+<secs> | move:<secs>
+    --best-of <games>      plays a match over several games
+    --first <rule>         who starts the next game of a match: alternate | loser
+    --tie-break <rule>     how a tied match gets decided: decider | second | none
+    --players <one>,<two>  names the players and keeps statistics of their games
+    --rating <system>      how ratings are computed: elo | glicko2
+    --board <board>        <rows>x<cols>x<win length>, like 7x7x5 (default 3x3x3), or a cube
+                           with the layers first, like 4x4x4x4
+    --gravity              marks fall to the bottom of the column, with --board 6x7x4 it is Connect Four
+    --misere               completing a line loses
+    --wild                 either player may set either mark, any line wins for whoever completes it
+    --wrap                 lines continue across the edges of the board, like on a torus
+    --variant <variant>    plays another game instead: ultimate | notakto[:<boards>] | morris[:adjacent|anywhere] | quantum | qubic | order
+
+Tournament options:
+    --engines <list>       random | greedy | minimax:<depth> | mcts:<playouts>, separated by commas
+    --games <games>        games per pairing (default 10)
+    --format <format>      round-robin | gauntlet (the first engine against all others)
+    --threads <threads>    how many games are played at once, spare threads help the search
+    --seed <seed>          makes the tournament reproducible
+    --record               records the games in the statistics, so engines get ratings
+
+Statistics and ratings are kept apart for every board, set of rules and variant.
+Pass the same --board, rule flags or --variant to stats, leaderboard, ratings and
+history to see them, without any the classic game is shown.";
+
+#[derive(Debug, PartialEq)]
+pub enum Subcommand {
+    STATS(String),
+    LEADERBOARD,
+    RATINGS,
+    HISTORY(String),
+    ANALYSE(String),
+    EVALUATE(String),
+    TABLEBASE,
+    BENCH,
+    TOURNAMENT,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Args {
+    pub time_control: Option<TimeControl>,
+    pub best_of: Option<usize>,
+    pub opening: Option<Opening>,
+    pub tie_break: Option<TieBreak>,
+    pub names: Option<[String; 2]>,
+    pub rating_system: System,
+    pub engines: Vec<Strategy>,
+    pub games: Option<usize>,
+    pub format: Option<Format>,
+    pub threads: Option<usize>,
+    pub seed: Option<u64>,
+    pub record: bool,
+    pub output: Option<String>,
+    pub shape: Option<Shape>,
+    pub rules: Rules,
+    pub variant: Option<Variant>,
+    pub subcommand: Option<Subcommand>,
+}
+
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+    let mut parsed = Args::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--time" => {
+                let value = args.next().ok_or("--time needs a time control")?;
+                parsed.time_control = Some(value.parse()?);
+            }
+            "--best-of" => {
+                let value = args.next().ok_or("--best-of needs a number of games")?;
+                match value.parse() {
+                    Ok(games) if games > 0 => parsed.best_of = Some(games),
+                    _ => return Err(format!("'{}' is not a number of games", value)),
+                }
+            }
+            "--first" => {
+                let value = args.next().ok_or("--first needs a rule")?;
+                parsed.opening = Some(value.parse()?);
+            }
+            "--tie-break" => {
+                let value = args.next().ok_or("--tie-break needs a rule")?;
+                parsed.tie_break = Some(value.parse()?);
+            }
+            "--players" => {
+                let value = args.next().ok_or("--players needs two names")?;
+                parsed.names = Some(parse_names(&value)?);
+            }
+            "stats" => {
+                let name = args.next().ok_or("stats needs the name of a player")?;
+                parsed.subcommand = Some(Subcommand::STATS(name));
+            }
+            "--rating" => {
+                let value = args.next().ok_or("--rating needs a rating system")?;
+                parsed.rating_system = value.parse()?;
+            }
+            "--engines" => {
+                let value = args.next().ok_or("--engines needs a list of strategies")?;
+                parsed.engines = value
+                    .split(',')
+                    .map(|strategy| strategy.trim().parse())
+                    .collect::<Result<_, _>>()?;
+                // The crosstable and the statistics tell engines apart by their strategy
+                for (idx, engine) in parsed.engines.iter().enumerate() {
+                    if parsed.engines[..idx].contains(engine) {
+                        return Err(format!("'{}' is listed twice in --engines", engine));
+                    }
+                }
+            }
+            "--games" => parsed.games = Some(parse_number(args.next(), "--games")?),
+            "--threads" => parsed.threads = Some(parse_number(args.next(), "--threads")?),
+            "--seed" => parsed.seed = Some(parse_number(args.next(), "--seed")?),
+            "--format" => {
+                let value = args.next().ok_or("--format needs a format")?;
+                parsed.format = Some(value.parse()?);
+            }
+            "--record" => parsed.record = true,
+            "tournament" => parsed.subcommand = Some(Subcommand::TOURNAMENT),
+            "leaderboard" => parsed.subcommand = Some(Subcommand::LEADERBOARD),
+            "ratings" => parsed.subcommand = Some(Subcommand::RATINGS),
+            "history" => {
+                let name = args.next().ok_or("history needs the name of a player")?;
+                parsed.subcommand = Some(Subcommand::HISTORY(name));
+            }
+            "analyse" | "analyze" => {
+                let path = args.next().ok_or("analyse needs a game record file")?;
+                parsed.subcommand = Some(Subcommand::ANALYSE(path));
+            }
+            "evaluate" => {
+                let position = args.next().ok_or("evaluate needs a position")?;
+                parsed.subcommand = Some(Subcommand::EVALUATE(position));
+            }
+            "tablebase" => parsed.subcommand = Some(Subcommand::TABLEBASE),
+            "bench" => parsed.subcommand = Some(Subcommand::BENCH),
+            "--board" => {
+                let value = args.next().ok_or("--board needs a size like 7x7x5")?;
+                parsed.shape = Some(value.parse()?);
+            }
+            "--gravity" => parsed.rules.gravity = true,
+            "--misere" => parsed.rules.misere = true,
+            "--wild" => parsed.rules.wild = true,
+            "--wrap" => parsed.rules.wrap = true,
+            "--variant" => {
+                let value = args.next().ok_or("--variant needs the name of a variant")?;
+                parsed.variant = Some(value.parse()?);
+            }
+            "--output" => {
+                let path = args.next().ok_or("--output needs a file")?;
+                parsed.output = Some(path);
+            }
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            unknown => return Err(format!("Unknown argument '{}'\n{}", unknown, USAGE)),
+        }
+    }
+    if parsed.rules.gravity && parsed.shape.is_some_and(|shape| shape.layers > 1) {
+        return Err("--gravity only works on a flat board".to_string());
+    }
+    if parsed.best_of.is_none() && (parsed.opening.is_some() || parsed.tie_break.is_some()) {
+        return Err("--first and --tie-break only apply to a match, add --best-of".to_string());
+    }
+    if parsed.variant.is_some()
+        && (parsed.shape.is_some()
+            || parsed.rules != Rules::default()
+            || parsed.best_of.is_some()
+            || parsed.time_control.is_some()
+            || parsed.names.is_some())
+    {
+        return Err(
+            "A variant comes with its own board and rules and is played without a clock \
+             or statistics. It can't be combined with --board, --gravity, --misere, --wild, \
+             --wrap, --best-of, --time or --players"
+                .to_string(),
+        );
+    }
+    Ok(parsed)
+}
+
+fn parse_number<N: std::str::FromStr>(value: Option<String>, option: &str) -> Result<N, String> {
+    let value = value.ok_or(format!("{} needs a number", option))?;
+    value
+        .parse()
+        .map_err(|_| format!("'{}' is not a valid number for {}", value, option))
+}
+
+fn parse_names(value: &str) -> Result<[String; 2], String> {
+    match value.split_once(',') {
+        Some((one, two)) => {
+            let (one, two) = (one.trim(), two.trim());
+            let valid = |name: &str| !name.is_empty() && !name.contains(['\t', ',']);
+            if valid(one) && valid(two) && one != two {
+                Ok([one.to_string(), two.to_string()])
+            } else {
+                Err(format!("'{}' are not two different names", value))
+            }
+        }
+        None => Err("--players needs two names separated by a comma".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::time::Duration;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn no_args_means_no_clock() {
+        assert_eq!(Ok(Args::default()), parse_args(args(&[])));
+    }
+
+    #[test]
+    fn can_parse_time_control() {
+        let parsed = parse_args(args(&["--time", "move:20"])).unwrap();
+        assert_eq!(
+            Some(TimeControl::MOVE(Duration::from_secs(20))),
+            parsed.time_control
+        );
+    }
+
+    #[test]
+    fn can_parse_match_options() {
+        let parsed = parse_args(args(&[
+            "--best-of",
+            "5",
+            "--first",
+            "loser",
+            "--tie-break",
+            "none",
+        ]))
+        .unwrap();
+        assert_eq!(Some(5), parsed.best_of);
+        assert_eq!(Some(Opening::LOSER), parsed.opening);
+        assert_eq!(Some(TieBreak::NONE), parsed.tie_break);
+        assert!(parse_args(args(&["--first", "loser"])).is_err());
+        assert!(parse_args(args(&["--tie-break", "second"])).is_err());
+    }
+
+    #[test]
+    fn can_parse_player_names() {
+        let parsed = parse_args(args(&["--players", "ada, bob"])).unwrap();
+        assert_eq!(Some(["ada".to_string(), "bob".to_string()]), parsed.names);
+        assert!(parse_args(args(&["--players", "ada"])).is_err());
+        assert!(parse_args(args(&["--players", "ada,ada"])).is_err());
+        assert!(parse_args(args(&["--players", "ada,"])).is_err());
+    }
+
+    #[test]
+    fn can_parse_stats() {
+        let parsed = parse_args(args(&["stats", "ada"])).unwrap();
+        assert_eq!(
+            Some(Subcommand::STATS("ada".to_string())),
+            parsed.subcommand
+        );
+        let parsed = parse_args(args(&["leaderboard", "--board", "4x4", "--wild"])).unwrap();
+        assert_eq!(Some(Subcommand::LEADERBOARD), parsed.subcommand);
+        assert_eq!(Some("4x4".parse().unwrap()), parsed.shape);
+        assert!(parsed.rules.wild);
+        assert!(parse_args(args(&["stats"])).is_err());
+    }
+
+    #[test]
+    fn can_parse_ratings() {
+        let parsed = parse_args(args(&["ratings"])).unwrap();
+        assert_eq!(Some(Subcommand::RATINGS), parsed.subcommand);
+        let parsed = parse_args(args(&["--rating", "glicko2", "history", "ada"])).unwrap();
+        assert_eq!(System::GLICKO2, parsed.rating_system);
+        assert_eq!(
+            Some(Subcommand::HISTORY("ada".to_string())),
+            parsed.subcommand
+        );
+        assert!(parse_args(args(&["history"])).is_err());
+    }
+
+    #[test]
+    fn can_parse_analyse() {
+        let parsed = parse_args(args(&["analyse", "game.txt"])).unwrap();
+        assert_eq!(
+            Some(Subcommand::ANALYSE("game.txt".to_string())),
+            parsed.subcommand
+        );
+        assert!(parse_args(args(&["analyse"])).is_err());
+    }
+
+    #[test]
+    fn can_parse_bench() {
+        let parsed = parse_args(args(&["bench", "--board", "7x7x5"])).unwrap();
+        assert_eq!(Some(Subcommand::BENCH), parsed.subcommand);
+        assert_eq!(Some("7x7x5".parse().unwrap()), parsed.shape);
+    }
+
+    #[test]
+    fn can_parse_board_and_rules() {
+        assert!(parse_args(args(&["--board", "3x3x9"])).is_err());
+        let parsed = parse_args(args(&["--board", "6x7x4", "--gravity"])).unwrap();
+        assert!(parsed.rules.gravity);
+        assert!(parse_args(args(&["--misere"])).unwrap().rules.misere);
+        assert!(parse_args(args(&["--wild"])).unwrap().rules.wild);
+        let parsed = parse_args(args(&["--board", "4x4x4x4"])).unwrap();
+        assert_eq!(Some(Shape::cube(4)), parsed.shape);
+        assert!(parse_args(args(&["--board", "4x4x4x4", "--gravity"])).is_err());
+    }
+
+    #[test]
+    fn can_parse_wrap() {
+        assert!(parse_args(args(&["--wrap"])).unwrap().rules.wrap);
+    }
+
+    #[test]
+    fn can_parse_variants() {
+        let parsed = parse_args(args(&["tournament", "--variant", "ultimate"])).unwrap();
+        assert_eq!(Some(Variant::ULTIMATE), parsed.variant);
+        assert!(parse_args(args(&["--variant", "chess"])).is_err());
+        let parsed = parse_args(args(&["--variant", "notakto:2"])).unwrap();
+        assert_eq!(Some(Variant::NOTAKTO(2)), parsed.variant);
+        assert!(parse_args(args(&["--variant", "notakto:0"])).is_err());
+        let parsed = parse_args(args(&["--variant", "morris:anywhere"])).unwrap();
+        assert_eq!(Some(Variant::MORRIS { adjacent: false }), parsed.variant);
+        let parsed = parse_args(args(&["--variant", "quantum"])).unwrap();
+        assert_eq!(Some(Variant::QUANTUM), parsed.variant);
+        let parsed = parse_args(args(&["--variant", "qubic"])).unwrap();
+        assert_eq!(Some(Variant::QUBIC), parsed.variant);
+        let parsed = parse_args(args(&["--variant", "order"])).unwrap();
+        assert_eq!(Some(Variant::ORDER), parsed.variant);
+    }
+
+    #[test]
+    fn variants_come_with_their_own_rules() {
+        for option in [
+            &["--board", "4x4"][..],
+            &["--gravity"],
+            &["--misere"],
+            &["--wild"],
+            &["--wrap"],
+            &["--best-of", "3"],
+            &["--time", "move:20"],
+            &["--players", "ada,bob"],
+        ] {
+            let mut with_variant = args(&["--variant", "qubic"]);
+            with_variant.extend(args(option));
+            assert!(parse_args(with_variant).is_err(), "{:?}", option);
+        }
+    }
+
+    #[test]
+    fn can_parse_tablebase() {
+        let parsed = parse_args(args(&["tablebase", "--output", "table.bin"])).unwrap();
+        assert_eq!(Some(Subcommand::TABLEBASE), parsed.subcommand);
+        assert_eq!(Some("table.bin".to_string()), parsed.output);
+    }
+
+    #[test]
+    fn can_parse_evaluate() {
+        let parsed = parse_args(args(&["evaluate", "x../.o./... x"])).unwrap();
+        assert_eq!(
+            Some(Subcommand::EVALUATE("x../.o./... x".to_string())),
+            parsed.subcommand
+        );
+        assert!(parse_args(args(&["evaluate"])).is_err());
+    }
+
+    #[test]
+    fn can_parse_tournament_options() {
+        let parsed = parse_args(args(&[
+            "tournament",
+            "--engines",
+            "random, minimax:3,mcts:100",
+            "--games",
+            "20",
+            "--format",
+            "gauntlet",
+            "--threads",
+            "4",
+            "--seed",
+            "7",
+            "--record",
+        ]))
+        .unwrap();
+        assert_eq!(Some(Subcommand::TOURNAMENT), parsed.subcommand);
+        assert_eq!(
+            vec![Strategy::RANDOM, Strategy::MINIMAX(3), Strategy::MCTS(100)],
+            parsed.engines
+        );
+        assert_eq!(
+            (Some(20), Some(Format::GAUNTLET), Some(4), Some(7), true),
+            (
+                parsed.games,
+                parsed.format,
+                parsed.threads,
+                parsed.seed,
+                parsed.record
+            )
+        );
+        assert!(parse_args(args(&["--engines", "random,deep-blue"])).is_err());
+        assert!(parse_args(args(&["--engines", "minimax:2,random,minimax:2"])).is_err());
+        assert!(parse_args(args(&["--games", "many"])).is_err());
+    }
+
+    #[test]
+    fn missing_or_unknown_args_are_errors() {
+        assert!(parse_args(args(&["--time"])).is_err());
+        assert!(parse_args(args(&["--best-of", "0"])).is_err());
+        assert!(parse_args(args(&["--colour", "blue"])).is_err());
+    }
+}
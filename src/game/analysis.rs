@@ -0,0 +1,355 @@
+use crate::game::board::{Board, BoardState};
+use crate::game::engine::{self, Position, Turn, WIN};
+use crate::game::notation::{self, GameRecord};
+use crate::game::player::Player;
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::mem;
+
+/// What a position or a move is worth for the player to move, if both sides play perfectly.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Value {
+    /// Wins, the game ends after the given number of moves of both players.
+    WIN(usize),
+    DRAW,
+    /// Loses, the game ends after the given number of moves of both players.
+    LOSS(usize),
+}
+
+impl Value {
+    pub fn from_score(score: i32) -> Self {
+        match score {
+            0 => Self::DRAW,
+            score if score > 0 => Self::WIN((WIN - score) as usize),
+            score => Self::LOSS((WIN + score) as usize),
+        }
+    }
+
+    /// The value as a search scores it.
+    pub fn score(self) -> i32 {
+        match self {
+            Self::WIN(moves) => WIN - moves as i32,
+            Self::DRAW => 0,
+            Self::LOSS(moves) => -(WIN - moves as i32),
+        }
+    }
+
+    /// Whether both values are wins, draws or losses, whatever the distance.
+    pub fn same_outcome(self, other: Value) -> bool {
+        mem::discriminant(&self) == mem::discriminant(&other)
+    }
+}
+
+/// Faster wins are better, slower losses are less bad.
+impl Ord for Value {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.score().cmp(&other.score())
+    }
+}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let plural = |moves: usize| if moves == 1 { "" } else { "s" };
+        match *self {
+            Self::WIN(moves) => write!(f, "wins in {} move{}", moves, plural(moves)),
+            Self::DRAW => write!(f, "draws"),
+            Self::LOSS(moves) => write!(f, "loses in {} move{}", moves, plural(moves)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Evaluation {
+    /// The value of the position.
+    pub value: Value,
+    /// Every legal move and its value, both for the player to move.
+    pub moves: Vec<((usize, usize), Value)>,
+}
+
+impl Evaluation {
+    /// Every move that keeps the value of the position.
+    pub fn best_moves(&self) -> Vec<(usize, usize)> {
+        self.moves
+            .iter()
+            .filter(|(_, value)| *value == self.value)
+            .map(|(field, _)| *field)
+            .collect()
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec![format!("The player to move {}", self.value)];
+        lines.extend(
+            self.moves
+                .iter()
+                .map(|&(field, value)| format!("{:<4}{}", notation::cell_name(field), value)),
+        );
+        lines
+    }
+}
+
+/// Solves the position on `board` with `player` to move.
+pub fn evaluate(board: &Board, player: Player) -> Evaluation {
+    let turn = Turn {
+        board: board.clone(),
+        player,
+    };
+    let depth = board.empty_count();
+    let moves: Vec<((usize, usize), Value)> = engine::move_values(&turn, depth)
+        .into_iter()
+        .map(|(field, score)| (field, Value::from_score(score)))
+        .collect();
+    let value = match moves.iter().map(|(_, value)| *value).max() {
+        Some(value) => value,
+        None => match board.get_board_state() {
+            BoardState::WON(winner) if winner == player => Value::WIN(0),
+            BoardState::WON(_) => Value::LOSS(0),
+            _ => Value::DRAW,
+        },
+    };
+    Evaluation { value, moves }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Mark {
+    /// Keeps the outcome, but wins slower or loses faster than necessary.
+    INACCURACY,
+    /// Gives away a win or a draw.
+    BLUNDER,
+}
+
+impl Mark {
+    /// The symbol used in game records.
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Self::INACCURACY => "?!",
+            Self::BLUNDER => "??",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Annotation {
+    pub mark: Mark,
+    /// A perfect move in place of the played one.
+    pub better: (usize, usize),
+}
+
+/// Replays `moves` from the empty board and compares each one with a perfect move.
+/// Moves that were as good as possible get no annotation.
+pub fn annotate(first: Player, moves: &[(usize, usize)]) -> Vec<Option<Annotation>> {
+    let mut turn = Turn {
+        player: first,
+        ..Turn::default()
+    };
+    let mut annotations = Vec::with_capacity(moves.len());
+    for &field in moves {
+        let evaluation = evaluate(&turn.board, turn.player);
+        let played = evaluation
+            .moves
+            .iter()
+            .find(|(mv, _)| *mv == field)
+            .map(|(_, value)| *value);
+        let best = evaluation.best_moves().first().copied();
+        let annotation = match (played, best) {
+            (Some(played), Some(better)) if played < evaluation.value => {
+                let mark = if played.same_outcome(evaluation.value) {
+                    Mark::INACCURACY
+                } else {
+                    Mark::BLUNDER
+                };
+                Some(Annotation { mark, better })
+            }
+            _ => None,
+        };
+        annotations.push(annotation);
+        if !turn.legal_moves().contains(&field) {
+            break;
+        }
+        turn.play(field);
+    }
+    annotations
+}
+
+/// The annotated move list, ready to be printed.
+pub fn lines(record: &GameRecord) -> Vec<String> {
+    let name = |player: Player| match &record.names {
+        Some(names) => match player {
+            Player::ONE => names[0].clone(),
+            Player::TWO => names[1].clone(),
+        },
+        None => format!("Player {:?}", player),
+    };
+    let mut lines = vec!["Analysis".to_string(), String::new()];
+    let mut player = record.first;
+    let mut counts = [0, 0];
+    for (idx, mv) in record.moves.iter().enumerate() {
+        let mut line = format!(
+            "{:>2}. {:<10} {}",
+            idx + 1,
+            name(player),
+            notation::cell_name(mv.field)
+        );
+        if let Some(annotation) = mv.annotation {
+            let (kind, count) = match annotation.mark {
+                Mark::INACCURACY => ("inaccuracy", &mut counts[0]),
+                Mark::BLUNDER => ("blunder", &mut counts[1]),
+            };
+            *count += 1;
+            line.push_str(&format!(
+                " {} {}, {} was better",
+                annotation.mark.symbol(),
+                kind,
+                notation::cell_name(annotation.better)
+            ));
+        }
+        lines.push(line);
+        player = player.other();
+    }
+    lines.push(String::new());
+    lines.push(format!(
+        "Result: {}. {} inaccuracies, {} blunders",
+        record.result, counts[0], counts[1]
+    ));
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn board(marks: &[(Player, (usize, usize))]) -> Board {
+        let mut board = Board::default();
+        for &(player, field) in marks {
+            let _ = board.set_value(player, field);
+        }
+        board
+    }
+
+    #[test]
+    fn empty_board_is_a_draw() {
+        let evaluation = evaluate(&Board::default(), Player::ONE);
+        assert_eq!(Value::DRAW, evaluation.value);
+        assert_eq!(9, evaluation.moves.len());
+        assert_eq!(9, evaluation.best_moves().len());
+    }
+
+    #[test]
+    fn evaluation_tells_the_distance_to_the_end() {
+        // ONE has a1 and b1, TWO has a2 and b2, ONE to move wins at c1
+        let board = board(&[
+            (Player::ONE, (0, 0)),
+            (Player::ONE, (0, 1)),
+            (Player::TWO, (1, 0)),
+            (Player::TWO, (1, 1)),
+        ]);
+        let evaluation = evaluate(&board, Player::ONE);
+        assert_eq!(Value::WIN(1), evaluation.value);
+        assert_eq!(vec![(0, 2)], evaluation.best_moves());
+        // Anything but c1 or blocking at c2 lets TWO win at c2 right away
+        assert!(evaluation
+            .moves
+            .iter()
+            .filter(|(field, _)| ![(0, 2), (1, 2)].contains(field))
+            .all(|(_, value)| *value == Value::LOSS(2)));
+        assert_eq!(Value::WIN(1), evaluate(&board, Player::TWO).value);
+    }
+
+    #[test]
+    fn finished_position_has_no_moves() {
+        let board = board(&[
+            (Player::TWO, (0, 0)),
+            (Player::TWO, (1, 1)),
+            (Player::TWO, (2, 2)),
+        ]);
+        let evaluation = evaluate(&board, Player::ONE);
+        assert_eq!(Value::LOSS(0), evaluation.value);
+        assert!(evaluation.moves.is_empty());
+    }
+
+    #[test]
+    fn values_are_ordered_from_best_to_worst() {
+        let mut values = vec![
+            Value::LOSS(2),
+            Value::WIN(3),
+            Value::DRAW,
+            Value::LOSS(4),
+            Value::WIN(1),
+        ];
+        values.sort();
+        assert_eq!(
+            vec![
+                Value::LOSS(2),
+                Value::LOSS(4),
+                Value::DRAW,
+                Value::WIN(3),
+                Value::WIN(1)
+            ],
+            values
+        );
+        assert_eq!("wins in 1 move", Value::WIN(1).to_string());
+        assert_eq!("loses in 4 moves", Value::LOSS(4).to_string());
+    }
+
+    #[test]
+    fn perfect_game_has_no_annotations() {
+        // Centre, corner, opposite corner, corner, block, block, block, block, last cell
+        let moves = [
+            (1, 1),
+            (0, 0),
+            (2, 2),
+            (0, 2),
+            (0, 1),
+            (2, 1),
+            (1, 0),
+            (1, 2),
+            (2, 0),
+        ];
+        let annotations = annotate(Player::ONE, &moves);
+        assert_eq!(9, annotations.len());
+        assert!(annotations.iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn edge_reply_to_a_corner_is_a_blunder() {
+        let annotations = annotate(Player::ONE, &[(0, 0), (0, 1)]);
+        assert_eq!(None, annotations[0]);
+        assert_eq!(
+            Some(Annotation {
+                mark: Mark::BLUNDER,
+                better: (1, 1)
+            }),
+            annotations[1]
+        );
+    }
+
+    #[test]
+    fn slow_win_is_an_inaccuracy() {
+        // TWO misses the block, then ONE forks with 2,0 instead of winning at once with 0,2
+        let moves = [(0, 0), (1, 1), (0, 1), (2, 2), (2, 0)];
+        let annotations = annotate(Player::ONE, &moves);
+        assert_eq!(Some(Mark::BLUNDER), annotations[3].map(|a| a.mark));
+        assert_eq!(Some(Mark::INACCURACY), annotations[4].map(|a| a.mark));
+        assert_eq!(Some((0, 2)), annotations[4].map(|a| a.better));
+    }
+
+    #[test]
+    fn missing_the_block_is_a_blunder() {
+        let moves = [(0, 0), (1, 1), (0, 1), (2, 2)];
+        let annotations = annotate(Player::ONE, &moves);
+        assert_eq!(
+            Some(Annotation {
+                mark: Mark::BLUNDER,
+                better: (0, 2)
+            }),
+            annotations[3]
+        );
+    }
+}
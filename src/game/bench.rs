@@ -0,0 +1,215 @@
+use crate::game::board::{Board, BoardState, Rules, Shape};
+use crate::game::engine::rng::Rng;
+use crate::game::engine::{Engine, Strategy, Turn};
+use crate::game::player::Player;
+use crate::game::print_lines;
+
+use std::hint::black_box;
+use std::thread;
+use std::time::Instant;
+
+const POSITIONS: usize = 200;
+const ROUNDS: usize = 50;
+
+/// Times the win check, the check of the last move alone and move generation against walking
+/// the grid cell by cell, and a minimax search on up to `threads` threads, on `shape` or on
+/// a few typical boards.
+pub fn run(shape: Option<Shape>, threads: Option<usize>) {
+    let threads = threads
+        .unwrap_or_else(|| thread::available_parallelism().map_or(1, |threads| threads.get()));
+    let shapes = match shape {
+        Some(shape) => vec![shape],
+        None => ["3x3x3", "7x7x5", "15x15x5"]
+            .iter()
+            .map(|shape| shape.parse().expect("Known shapes parse"))
+            .collect(),
+    };
+    for shape in shapes {
+        let positions = random_positions(shape, POSITIONS, &mut Rng::seeded(1));
+        let mut lines = vec![
+            format!("Board {}, {} positions", shape, positions.len()),
+            format!(
+                "{:<18}{:>12}{:>12}{:>10}",
+                "", "grid scan", "board", "speedup"
+            ),
+            line(
+                "win check",
+                time(&positions, |sample| scan_state(&sample.board)),
+                time(&positions, |sample| sample.board.get_board_state()),
+            ),
+            line(
+                "last-move check",
+                time(&positions, |sample| scan_state(&sample.board)),
+                time(&positions, |sample| {
+                    sample.last.map(|field| sample.board.completes_line(field))
+                }),
+            ),
+            line(
+                "move generation",
+                time(&positions, |sample| scan_moves(&sample.board)),
+                time(&positions, |sample| {
+                    sample.board.empty_cells().collect::<Vec<(usize, usize)>>()
+                }),
+            ),
+        ];
+        lines.push(String::new());
+        lines.extend(search_lines(shape, threads));
+        print_lines(lines);
+    }
+}
+
+/// How long a search from the empty board takes on 1, 2, 4, .. and `threads` threads.
+fn search_lines(shape: Shape, threads: usize) -> Vec<String> {
+    let depth = search_depth(shape);
+    let mut counts: Vec<usize> = (0..)
+        .map(|power| 1 << power)
+        .take_while(|&count| count < threads)
+        .collect();
+    counts.push(threads);
+    let mut lines = vec![
+        format!("minimax:{} from the empty board", depth),
+        format!("{:<18}{:>12}{:>12}", "threads", "time", "speedup"),
+    ];
+    let mut single = None;
+    for count in counts {
+        let mut engine = Engine::new(Strategy::MINIMAX(depth))
+            .with_seed(1)
+            .with_threads(count);
+        let start = Instant::now();
+        black_box(engine.choose(&Turn::new(shape, Rules::default())));
+        let millis = start.elapsed().as_secs_f64() * 1000.0;
+        let single = *single.get_or_insert(millis);
+        lines.push(format!(
+            "{:<18}{:>9.1} ms{:>11.1}x",
+            count,
+            millis,
+            single / millis
+        ));
+    }
+    lines
+}
+
+/// Deep enough to take a moment, without solving the classic board from the tablebase.
+fn search_depth(shape: Shape) -> usize {
+    match shape.rows * shape.cols {
+        cells if cells <= 9 => cells - 1,
+        cells if cells <= 25 => 5,
+        cells if cells <= 64 => 4,
+        _ => 2,
+    }
+}
+
+fn line(name: &str, scan: f64, bits: f64) -> String {
+    format!(
+        "{:<18}{:>9.0} ns{:>9.0} ns{:>9.1}x",
+        name,
+        scan,
+        bits,
+        scan / bits
+    )
+}
+
+/// Nanoseconds per call.
+fn time<T>(positions: &[Sample], f: impl Fn(&Sample) -> T) -> f64 {
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        for sample in positions {
+            black_box(f(black_box(sample)));
+        }
+    }
+    start.elapsed().as_nanos() as f64 / (ROUNDS * positions.len()) as f64
+}
+
+struct Sample {
+    board: Board,
+    /// The move that led to the position, the only one the board has to check for a win.
+    last: Option<(usize, usize)>,
+}
+
+/// Positions from random games, some of them finished.
+fn random_positions(shape: Shape, count: usize, rng: &mut Rng) -> Vec<Sample> {
+    (0..count)
+        .map(|_| {
+            let mut board = Board::new(shape);
+            let mut last = None;
+            let mut player = Player::ONE;
+            let moves = rng.below(shape.rows * shape.cols + 1);
+            for _ in 0..moves {
+                let empty: Vec<(usize, usize)> = board.empty_cells().collect();
+                match rng.pick(&empty) {
+                    Some(&field) if board.get_board_state() == BoardState::ONGOING => {
+                        let _ = board.set_value(player, field);
+                        last = Some(field);
+                    }
+                    _ => break,
+                }
+                player = player.other();
+            }
+            Sample { board, last }
+        })
+        .collect()
+}
+
+/// The win check without bitboards: every run of cells is walked on the grid.
+fn scan_state(board: &Board) -> BoardState {
+    let shape = board.get_shape();
+    let length = shape.win_length as isize;
+    for (d_row, d_col) in [(0, 1), (1, 0), (1, 1), (1, -1)] {
+        for row in 0..shape.rows as isize {
+            for col in 0..shape.cols as isize {
+                let (last_row, last_col) = (row + d_row * (length - 1), col + d_col * (length - 1));
+                if !(0..shape.rows as isize).contains(&last_row)
+                    || !(0..shape.cols as isize).contains(&last_col)
+                {
+                    continue;
+                }
+                let mut cells = (0..length).map(|step| {
+                    board[(row + d_row * step) as usize][(col + d_col * step) as usize]
+                });
+                if let Some(Some(first)) = cells.next() {
+                    if cells.all(|cell| cell == Some(first)) {
+                        return BoardState::WON(first);
+                    }
+                }
+            }
+        }
+    }
+    if board.iter().flatten().any(|cell| cell.is_none()) {
+        BoardState::ONGOING
+    } else {
+        BoardState::DRAW
+    }
+}
+
+fn scan_moves(board: &Board) -> Vec<(usize, usize)> {
+    board
+        .iter()
+        .enumerate()
+        .flat_map(|(row, cells)| {
+            cells
+                .iter()
+                .enumerate()
+                .filter(|(_, cell)| cell.is_none())
+                .map(move |(col, _)| (row, col))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn bitboards_agree_with_the_grid() {
+        for shape in ["3x3x3", "7x7x5", "6x7x4", "15x15x5", "1x5x2"] {
+            let shape: Shape = shape.parse().unwrap();
+            for Sample { board, last } in random_positions(shape, 100, &mut Rng::seeded(9)) {
+                let won = matches!(board.get_board_state(), BoardState::WON(_));
+                assert_eq!(won, last.is_some_and(|field| board.completes_line(field)));
+                assert_eq!(scan_state(&board), board.get_board_state(), "{}", shape);
+                assert_eq!(scan_moves(&board), board.empty_cells().collect::<Vec<_>>());
+            }
+        }
+    }
+}
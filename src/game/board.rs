@@ -1,115 +1,787 @@
 use crate::game::player::Player;
 
-use std::ops::{Deref, DerefMut};
+use std::fmt;
+use std::ops::{Deref, DerefMut, Index, IndexMut};
+use std::str::FromStr;
+use std::sync::Arc;
 
 #[derive(Debug, PartialEq)]
 pub enum BoardState {
     ONGOING,
     WON(Player),
     DRAW,
+    /// The game was decided off the board. Holds the winner, if there is one.
+    ENDED(Option<Player>, Reason),
 }
 
-type Values = [[Option<Player>; 3]; 3];
+impl BoardState {
+    /// How the game ended, or `None` while it is still going on.
+    pub fn result(&self) -> Option<GameResult> {
+        let (winner, reason) = match *self {
+            BoardState::ONGOING => return None,
+            BoardState::WON(winner) => (Some(winner), Reason::LINE),
+            BoardState::DRAW => (None, Reason::FULL),
+            BoardState::ENDED(winner, reason) => (winner, reason),
+        };
+        Some(GameResult { winner, reason })
+    }
+}
+
+/// Why a game ended.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Reason {
+    LINE,
+    FULL,
+    RESIGNATION,
+    AGREEMENT,
+    TIMEOUT,
+    ABANDONMENT,
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            Self::LINE => "line completed",
+            Self::FULL => "board full",
+            Self::RESIGNATION => "resignation",
+            Self::AGREEMENT => "agreed draw",
+            Self::TIMEOUT => "timeout",
+            Self::ABANDONMENT => "abandonment",
+        };
+        write!(f, "{}", reason)
+    }
+}
+
+impl FromStr for Reason {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [
+            Self::LINE,
+            Self::FULL,
+            Self::RESIGNATION,
+            Self::AGREEMENT,
+            Self::TIMEOUT,
+            Self::ABANDONMENT,
+        ]
+        .iter()
+        .copied()
+        .find(|reason| reason.to_string() == s)
+        .ok_or_else(|| format!("Unknown reason '{}'", s))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct GameResult {
+    pub winner: Option<Player>,
+    pub reason: Reason,
+}
+
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let score = match self.winner {
+            Some(Player::ONE) => "1-0",
+            Some(Player::TWO) => "0-1",
+            None if self.reason == Reason::ABANDONMENT => "*",
+            None => "½-½",
+        };
+        write!(f, "{} ({})", score, self.reason)
+    }
+}
+
+/// The most cells a board can have.
+pub const MAX_CELLS: usize = 64 * WORDS;
+const WORDS: usize = 4;
+
+/// Rules that change how marks are placed, on a board of any shape.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct Rules {
+    /// Marks fall to the lowest empty cell of their column, like in Connect Four.
+    pub gravity: bool,
+    /// Completing a line loses instead of winning.
+    pub misere: bool,
+    /// Each turn the player to move picks which of the two marks to set.
+    pub wild: bool,
+    /// Lines continue across the edges on the other side, as if the board were a torus.
+    pub wrap: bool,
+}
+
+/// How big the board is and how many marks in a row win.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Shape {
+    pub rows: usize,
+    pub cols: usize,
+    pub win_length: usize,
+    /// With more than one layer the board is a cube, and lines also run through the layers.
+    pub layers: usize,
+}
+
+impl Default for Shape {
+    fn default() -> Self {
+        Shape {
+            rows: 3,
+            cols: 3,
+            win_length: 3,
+            layers: 1,
+        }
+    }
+}
+
+impl Shape {
+    /// `size` layers of `size` by `size` cells, where `size` in a row win.
+    pub fn cube(size: usize) -> Self {
+        Shape {
+            rows: size,
+            cols: size,
+            win_length: size,
+            layers: size,
+        }
+    }
+
+    /// The field of a cell of a layer. The layers are stacked, so the rows of the second
+    /// layer follow the last row of the first one.
+    pub fn field(&self, layer: usize, row: usize, col: usize) -> (usize, usize) {
+        (layer * self.rows + row, col)
+    }
+
+    fn cells(&self) -> usize {
+        self.layers * self.rows * self.cols
+    }
+
+    fn index(&self, (layer, row, col): (usize, usize, usize)) -> usize {
+        (layer * self.rows + row) * self.cols + col
+    }
+
+    /// The cell `steps` steps along `direction` from `cell`, `None` if that is off the board.
+    /// With `wrap`, leaving a row or a column comes back in on its other end.
+    fn step(
+        &self,
+        cell: (usize, usize, usize),
+        direction: (isize, isize, isize),
+        steps: isize,
+        wrap: bool,
+    ) -> Option<(usize, usize, usize)> {
+        let along = |start: usize, d: isize, size: usize, wraps: bool| {
+            let at = start as isize + d * steps;
+            if wraps {
+                Some(at.rem_euclid(size as isize) as usize)
+            } else if (0..size as isize).contains(&at) {
+                Some(at as usize)
+            } else {
+                None
+            }
+        };
+        Some((
+            along(cell.0, direction.0, self.layers, false)?,
+            along(cell.1, direction.1, self.rows, wrap)?,
+            along(cell.2, direction.2, self.cols, wrap)?,
+        ))
+    }
+
+    /// Every cell as its layer, row and column.
+    fn all_cells(self) -> impl Iterator<Item = (usize, usize, usize)> {
+        (0..self.cells()).map(move |idx| {
+            let in_layer = idx % (self.rows * self.cols);
+            (
+                idx / (self.rows * self.cols),
+                in_layer / self.cols,
+                in_layer % self.cols,
+            )
+        })
+    }
+}
+
+/// Cubes are written with the layers first, like `4x4x4x4`.
+impl fmt::Display for Shape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.layers > 1 {
+            write!(f, "{}x", self.layers)?;
+        }
+        write!(f, "{}x{}x{}", self.rows, self.cols, self.win_length)
+    }
+}
+
+/// Parses `<rows>x<cols>x<win length>`, like `7x7x5`. Without the win length,
+/// a full row or column of the shorter side wins. A cube has its layers first, like `4x4x4x4`.
+impl FromStr for Shape {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let numbers: Vec<usize> = s
+            .split('x')
+            .map(|number| number.parse().ok().filter(|&number| number > 0))
+            .collect::<Option<_>>()
+            .ok_or_else(|| format!("'{}' is not a board like 3x3, 7x7x5 or 4x4x4x4", s))?;
+        let shape = match numbers[..] {
+            [rows, cols] => Shape {
+                rows,
+                cols,
+                win_length: rows.min(cols),
+                layers: 1,
+            },
+            [rows, cols, win_length] => Shape {
+                rows,
+                cols,
+                win_length,
+                layers: 1,
+            },
+            [layers, rows, cols, win_length] => Shape {
+                rows,
+                cols,
+                win_length,
+                layers,
+            },
+            _ => return Err(format!("'{}' is not a board like 3x3, 7x7x5 or 4x4x4x4", s)),
+        };
+        if shape.cells() > MAX_CELLS {
+            return Err(format!("A board has at most {} cells", MAX_CELLS));
+        }
+        if shape.win_length > shape.rows.max(shape.cols).max(shape.layers) {
+            return Err(format!(
+                "{} in a row don't fit on the board",
+                shape.win_length
+            ));
+        }
+        Ok(shape)
+    }
+}
+
+/// A set of cells, one bit each, numbered row by row.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Bits([u64; WORDS]);
+
+impl Bits {
+    fn first(count: usize) -> Self {
+        let mut bits = Bits::default();
+        for idx in 0..count {
+            bits.set(idx);
+        }
+        bits
+    }
+
+    fn one(idx: usize) -> Self {
+        let mut bits = Bits::default();
+        bits.set(idx);
+        bits
+    }
+
+    fn set(&mut self, idx: usize) {
+        self.0[idx / 64] |= 1 << (idx % 64);
+    }
+
+    fn union(self, other: Bits) -> Self {
+        let mut union = self;
+        for (word, other) in union.0.iter_mut().zip(other.0) {
+            *word |= other;
+        }
+        union
+    }
+
+    fn without(self, other: Bits) -> Self {
+        let mut rest = self;
+        for (word, other) in rest.0.iter_mut().zip(other.0) {
+            *word &= !other;
+        }
+        rest
+    }
+
+    fn count(&self) -> usize {
+        self.0.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.iter().all(|&word| word == 0)
+    }
+
+    fn intersect(self, other: Bits) -> Self {
+        let mut both = self;
+        for (word, other) in both.0.iter_mut().zip(other.0) {
+            *word &= other;
+        }
+        both
+    }
+
+    /// Moves every cell `by` numbers down: cell `idx + by` becomes cell `idx`.
+    fn shifted(self, by: usize) -> Self {
+        let (words, bits) = (by / 64, by % 64);
+        let mut shifted = Bits::default();
+        for idx in 0..WORDS.saturating_sub(words) {
+            let low = self.0[idx + words] >> bits;
+            let high = match self.0.get(idx + words + 1) {
+                Some(&next) if bits > 0 => next << (64 - bits),
+                _ => 0,
+            };
+            shifted.0[idx] = low | high;
+        }
+        shifted
+    }
+
+    /// The numbers of the cells in the set, in order.
+    fn ones(self) -> Ones {
+        Ones(self)
+    }
+}
+
+struct Ones(Bits);
+
+impl Iterator for Ones {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let word_idx = self.0 .0.iter().position(|&word| word != 0)?;
+        let word = &mut self.0 .0[word_idx];
+        let bit = word.trailing_zeros() as usize;
+        *word &= *word - 1;
+        Some(word_idx * 64 + bit)
+    }
+}
+
+/// A run of cells that wins, and the range of words of its bits that aren't zero.
+#[derive(Debug, PartialEq, Eq)]
+struct Window {
+    bits: Bits,
+    first_word: usize,
+    last_word: usize,
+}
+
+impl Window {
+    fn new(cells: impl Iterator<Item = usize>) -> Self {
+        let mut bits = Bits::default();
+        cells.for_each(|idx| bits.set(idx));
+        let used = |word: &u64| *word != 0;
+        Window {
+            bits,
+            first_word: bits.0.iter().position(used).unwrap_or(0),
+            last_word: bits.0.iter().rposition(used).unwrap_or(0),
+        }
+    }
 
-#[derive(Debug, Default)]
+    fn held_by(&self, cells: &Bits) -> bool {
+        (self.first_word..=self.last_word)
+            .all(|word| cells.0[word] & self.bits.0[word] == self.bits.0[word])
+    }
+}
+
+/// The cells row by row, to be read like nested arrays: `grid[row][col]`. The layers of a
+/// cube are stacked, see `Shape::field`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grid {
+    cols: usize,
+    cells: Vec<Option<Player>>,
+}
+
+impl Grid {
+    /// The number of rows.
+    pub fn len(&self) -> usize {
+        self.cells.len() / self.cols
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::ChunksExact<'_, Option<Player>> {
+        self.cells.chunks_exact(self.cols)
+    }
+}
+
+impl Index<usize> for Grid {
+    type Output = [Option<Player>];
+
+    fn index(&self, row: usize) -> &Self::Output {
+        &self.cells[row * self.cols..(row + 1) * self.cols]
+    }
+}
+
+impl IndexMut<usize> for Grid {
+    fn index_mut(&mut self, row: usize) -> &mut Self::Output {
+        &mut self.cells[row * self.cols..(row + 1) * self.cols]
+    }
+}
+
+impl<const ROWS: usize, const COLS: usize> PartialEq<Grid> for [[Option<Player>; COLS]; ROWS] {
+    fn eq(&self, grid: &Grid) -> bool {
+        self.len() == grid.len()
+            && self
+                .iter()
+                .zip(grid.iter())
+                .all(|(row, other)| row == other)
+    }
+}
+
+/// The cells are kept twice: as a grid to read from, and as one set of bits per player,
+/// which the win check compares against the precomputed windows that win.
+#[derive(Debug, Clone)]
 pub struct Board {
-    values: Values,
+    grid: Grid,
+    shape: Shape,
+    rules: Rules,
+    /// The cells of Player ONE and of Player TWO.
+    bits: [Bits; 2],
+    /// All cells of the board.
+    all: Bits,
+    /// For each direction, how far a step moves in the numbering of the cells and where a
+    /// winning run can start.
+    runs: Arc<[(usize, Bits)]>,
+    /// Every `win_length` cells in a row, a column or a diagonal. Shared by all copies.
+    windows: Arc<[Window]>,
+    /// The Zobrist hash of the cells, updated with every mark that is set or cleared.
+    hash: u64,
+    /// Set by the move that completes a line, so the state needs no scan of the board.
+    completed_by: Option<Player>,
+    /// The field of that move, the line through it decides whether the game is still won
+    /// after a mark is taken back.
+    completed_at: Option<(usize, usize)>,
+    empty: usize,
+    /// Set when the grid was changed in place, the bits, the hash and the counts are rebuilt
+    /// from it then.
+    stale: bool,
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Board::new(Shape::default())
+    }
 }
 
 impl Deref for Board {
-    type Target = Values;
+    type Target = Grid;
 
     fn deref(&self) -> &Self::Target {
-        &self.values
+        &self.grid
     }
 }
 
+/// Cells changed in place have no mover, a line among them goes to the owner of its marks.
+/// Moves go through `set_mark`, this is for setting up positions.
 impl DerefMut for Board {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.values
+        self.stale = true;
+        &mut self.grid
     }
 }
 
 impl Board {
-    pub fn draw(&self) {
-        let print_sep = || println!("      ----+----+----");
-
-        println!("      col  col  col");
-        println!("       1    2    3");
-        for (line_num, line) in self.values.iter().enumerate() {
-            println!("row {} {}", line_num + 1, gen_line(&line));
-            if should_print_seperator(line_num, self.values.len()) {
-                print_sep();
-            }
+    pub fn new(shape: Shape) -> Self {
+        Board {
+            grid: Grid {
+                cols: shape.cols,
+                cells: vec![None; shape.cells()],
+            },
+            shape,
+            rules: Rules::default(),
+            bits: [Bits::default(); 2],
+            all: Bits::first(shape.cells()),
+            runs: runs(shape).into(),
+            windows: windows(shape, false).into(),
+            hash: 0,
+            completed_by: None,
+            completed_at: None,
+            empty: shape.cells(),
+            stale: false,
         }
     }
 
-    fn get_diags(
-        &self,
-    ) -> (
-        impl Iterator<Item = Option<Player>> + Clone + '_,
-        impl Iterator<Item = Option<Player>> + Clone + '_,
-    ) {
-        let tl_br = self.iter().enumerate().map(|(idx, row)| row[idx]);
-        let bl_tr = self.iter().rev().enumerate().map(|(idx, row)| row[idx]);
-        (tl_br, bl_tr)
+    pub fn with_rules(mut self, rules: Rules) -> Self {
+        if rules.wrap != self.rules.wrap {
+            self.windows = windows(self.shape, rules.wrap).into();
+        }
+        self.rules = rules;
+        self
     }
 
-    fn get_rows(
-        &self,
-    ) -> impl Iterator<Item = impl Iterator<Item = Option<Player>> + Clone + '_> + Clone + '_ {
-        self.iter().map(|row| row.iter().map(|&item| item))
+    pub fn draw(&self) {
+        for line in self.draw_lines() {
+            println!("{}", line);
+        }
     }
 
-    fn get_cols(
-        &self,
-    ) -> impl Iterator<Item = impl Iterator<Item = Option<Player>> + Clone + '_> + Clone + '_ {
-        (0..3).map(move |row| (0..3).map(move |col| self.values[col][row]))
+    /// The lines `draw` prints. The layers of a cube come one below the other. A board that
+    /// wraps gets ghost rows and columns around it, which repeat the opposite edge.
+    pub fn draw_lines(&self) -> Vec<String> {
+        let (rows, wrap) = (self.shape.rows, self.rules.wrap);
+        let label_width = rows.to_string().len();
+        let indent = " ".repeat(label_width + 5);
+        let cols_indent = if wrap {
+            format!("{}     ", indent)
+        } else {
+            indent.clone()
+        };
+        let separator = format!("{}{}", cols_indent, vec!["----"; self.shape.cols].join("+"));
+
+        let mut lines = vec![format!(
+            "{}{}",
+            cols_indent,
+            vec!["col"; self.shape.cols].join("  ")
+        )];
+        let numbers: Vec<String> = (1..=self.shape.cols)
+            .map(|col| format!("{:^4}", col))
+            .collect();
+        lines.push(format!("{}{}", cols_indent, numbers.join(" ").trim_end()));
+        for (line_num, line) in self.grid.iter().enumerate() {
+            let (layer, row) = (line_num / rows, line_num % rows);
+            if self.shape.layers > 1 && row == 0 {
+                lines.push(String::new());
+                lines.push(format!("layer {}", layer + 1));
+            }
+            if wrap && row == 0 {
+                lines.push(format!(
+                    "{}{}",
+                    indent,
+                    gen_ghost_line(&self.grid[line_num + rows - 1])
+                ));
+            }
+            let line = if wrap {
+                let last = line.len() - 1;
+                format!(
+                    "{}:{}:{}",
+                    ghost(line[last]),
+                    gen_line(line),
+                    ghost(line[0])
+                )
+            } else {
+                gen_line(line)
+            };
+            lines.push(format!(
+                "row {:<width$} {}",
+                row + 1,
+                line,
+                width = label_width
+            ));
+            if should_print_seperator(row, rows) {
+                lines.push(separator.clone());
+            }
+            if wrap && row == rows - 1 {
+                lines.push(format!(
+                    "{}{}",
+                    indent,
+                    gen_ghost_line(&self.grid[line_num + 1 - rows])
+                ));
+            }
+        }
+        if wrap {
+            lines.push(String::new());
+            lines.push(
+                "The board wraps around, the marks in ( ) repeat the opposite edge.".to_string(),
+            );
+        }
+        lines
     }
 
     pub fn get_board_state(&self) -> BoardState {
-        if let Some(player) = self.get_winner() {
-            return BoardState::WON(player);
+        let completed_by = if self.stale {
+            self.get_winner()
+        } else {
+            self.completed_by
+        };
+        if let Some(player) = completed_by {
+            return BoardState::WON(if self.rules.misere {
+                player.other()
+            } else {
+                player
+            });
         }
-        if (*self).iter().flatten().any(|cell| cell.is_none()) {
+        if self.empty_count() > 0 {
             return BoardState::ONGOING;
         }
         BoardState::DRAW
     }
 
-    fn get_winner(&self) -> Option<Player> {
-        let diags = self.get_diags();
-        if let Some(winner) = check_for_winner(diags.0) {
-            return Some(winner);
-        }
-        if let Some(winner) = check_for_winner(diags.1) {
-            return Some(winner);
+    /// Whether the mark on `field` is part of a full line of its owner. Only looks at the
+    /// lines through `field`, at most `win_length - 1` cells to each side.
+    pub fn completes_line(&self, field: (usize, usize)) -> bool {
+        if field.0 >= self.grid.len() || field.1 >= self.shape.cols {
+            return false;
         }
-        for row in self.get_rows() {
-            if let Some(winner) = check_for_winner(row) {
-                return Some(winner);
+        let cell = (
+            field.0 / self.shape.rows,
+            field.0 % self.shape.rows,
+            field.1,
+        );
+        let player = match self.mark(cell) {
+            Some(player) => player,
+            None => return false,
+        };
+        let (length, wrap) = (self.shape.win_length, self.rules.wrap);
+        directions(self.shape).iter().any(|&direction| {
+            if wrap && period(self.shape, direction) < length {
+                return false;
             }
+            let run = |sign: isize| {
+                (1..length as isize)
+                    .take_while(|&steps| {
+                        self.shape
+                            .step(cell, direction, sign * steps, wrap)
+                            .is_some_and(|next| self.mark(next) == Some(player))
+                    })
+                    .count()
+            };
+            1 + run(1) + run(-1) >= length
+        })
+    }
+
+    /// The mark on a cell given as its layer, row and column.
+    fn mark(&self, (layer, row, col): (usize, usize, usize)) -> Option<Player> {
+        self.grid[layer * self.shape.rows + row][col]
+    }
+
+    /// Looks through the whole board for a player with a full line.
+    fn get_winner(&self) -> Option<Player> {
+        let bits = self.bits();
+        [Player::ONE, Player::TWO].iter().copied().find(|&player| {
+            let cells = &bits[player.index()];
+            if cells.count() < self.shape.win_length {
+                false
+            } else if self.windows.len() <= FEW_WINDOWS || self.rules.wrap {
+                self.windows.iter().any(|window| window.held_by(cells))
+            } else {
+                self.has_run(cells)
+            }
+        })
+    }
+
+    /// The cells of Player ONE and of Player TWO, read from the grid if it was changed in place.
+    fn bits(&self) -> [Bits; 2] {
+        if !self.stale {
+            return self.bits;
         }
-        for col in self.get_cols() {
-            if let Some(winner) = check_for_winner(col) {
-                return Some(winner);
+        let mut bits = [Bits::default(); 2];
+        for (idx, cell) in self.grid.cells.iter().enumerate() {
+            if let Some(player) = cell {
+                bits[player.index()].set(idx);
             }
         }
-        None
+        bits
+    }
+
+    /// Rebuilds what is kept next to the grid after the grid was changed in place.
+    fn sync(&mut self) {
+        if self.stale {
+            self.bits = self.bits();
+            self.hash = self.hash();
+            self.empty = self.empty_count();
+            self.completed_by = self.get_winner();
+            self.completed_at = None;
+            self.stale = false;
+        }
+    }
+
+    /// Shifts the cells one step along a direction at a time and keeps only the starts that
+    /// are still followed by own cells. Whatever is left is the start of a full run.
+    fn has_run(&self, cells: &Bits) -> bool {
+        self.runs.iter().any(|&(step, starts)| {
+            let mut run = cells.intersect(starts);
+            for length in 1..self.shape.win_length {
+                if run.is_empty() {
+                    break;
+                }
+                run = run.intersect(cells.shifted(length * step));
+            }
+            !run.is_empty()
+        })
     }
 }
 
-fn check_for_winner(mut cells: impl Iterator<Item = Option<Player>> + Clone) -> Option<Player> {
-    if cells.clone().all(|p| p == Some(Player::ONE)) {
-        Some(Player::ONE)
-    } else if cells.all(|p| p == Some(Player::TWO)) {
-        Some(Player::TWO)
+/// As steps of layer, row and column. First the ones within a layer: row, column, diagonal to
+/// the lower right and diagonal to the lower left. Then the ones through the layers: the
+/// pillar, four diagonals along the sides and four space diagonals.
+const DIRECTIONS: [(isize, isize, isize); 13] = [
+    (0, 0, 1),
+    (0, 1, 0),
+    (0, 1, 1),
+    (0, 1, -1),
+    (1, 0, 0),
+    (1, 0, 1),
+    (1, 0, -1),
+    (1, 1, 0),
+    (1, -1, 0),
+    (1, 1, 1),
+    (1, 1, -1),
+    (1, -1, 1),
+    (1, -1, -1),
+];
+
+/// The directions a line can take, only the ones within a layer on a flat board.
+fn directions(shape: Shape) -> &'static [(isize, isize, isize)] {
+    if shape.layers > 1 {
+        &DIRECTIONS
     } else {
-        None
+        &DIRECTIONS[..4]
     }
 }
 
-fn gen_line(values: &[Option<Player>; 3]) -> String {
+/// Up to this many windows, checking each of them is faster than shifting the bits.
+const FEW_WINDOWS: usize = 64;
+
+/// How many steps along `direction` lead back to the same cell when the rows and columns wrap,
+/// `usize::MAX` if they never do. A line needs that many different cells at least.
+fn period(shape: Shape, (d_layer, d_row, d_col): (isize, isize, isize)) -> usize {
+    match (d_layer, d_row, d_col) {
+        (0, 0, _) => shape.cols,
+        (0, _, 0) => shape.rows,
+        (0, _, _) => shape.rows / gcd(shape.rows, shape.cols) * shape.cols,
+        _ => usize::MAX,
+    }
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    match b {
+        0 => a,
+        b => gcd(b, a % b),
+    }
+}
+
+/// For each direction, how far one step moves in the numbering of the cells, and every cell
+/// where a winning run can start. Shifting the numbering can't wrap around.
+fn runs(shape: Shape) -> Vec<(usize, Bits)> {
+    let reach = shape.win_length as isize - 1;
+    directions(shape)
+        .iter()
+        .map(|&direction| {
+            let (d_layer, d_row, d_col) = direction;
+            let step = (d_layer * shape.rows as isize + d_row) * shape.cols as isize + d_col;
+            let mut starts = Bits::default();
+            for cell in shape.all_cells() {
+                if shape.step(cell, direction, reach, false).is_some() {
+                    starts.set(shape.index(cell));
+                }
+            }
+            (step.max(0) as usize, starts)
+        })
+        .collect()
+}
+
+/// Every run of `win_length` cells along a row, a column, a diagonal or, on a cube, through
+/// the layers. With `wrap`, also the runs that cross an edge.
+fn windows(shape: Shape, wrap: bool) -> Vec<Window> {
+    let mut windows = Vec::new();
+    for &direction in directions(shape) {
+        if wrap && period(shape, direction) < shape.win_length {
+            continue;
+        }
+        for cell in shape.all_cells() {
+            let cells: Option<Vec<usize>> = (0..shape.win_length as isize)
+                .map(|steps| {
+                    shape
+                        .step(cell, direction, steps, wrap)
+                        .map(|cell| shape.index(cell))
+                })
+                .collect();
+            let window = match cells {
+                Some(cells) => Window::new(cells.into_iter()),
+                None => continue,
+            };
+            // A single cell lies in every direction, and a run around a whole row is the same
+            // from every start, they only count once
+            if !windows.contains(&window) {
+                windows.push(window);
+            }
+        }
+    }
+    windows
+}
+
+fn gen_line(values: &[Option<Player>]) -> String {
     values
         .iter()
         .map(|p| match p {
@@ -120,32 +792,232 @@ fn gen_line(values: &[Option<Player>; 3]) -> String {
         .join("|")
 }
 
+/// A mark repeated from the other side of a board that wraps.
+fn ghost(value: Option<Player>) -> String {
+    match value {
+        Some(p) => format!("({})", p),
+        None => "    ".to_string(),
+    }
+}
+
+/// A whole row repeated from the other side, its corners from the opposite columns.
+fn gen_ghost_line(values: &[Option<Player>]) -> String {
+    let cells: Vec<String> = values.iter().map(|&value| ghost(value)).collect();
+    format!(
+        "{}:{}:{}",
+        ghost(values[values.len() - 1]),
+        cells.join(" "),
+        ghost(values[0])
+    )
+}
+
 fn should_print_seperator(line_num: usize, board_height: usize) -> bool {
     line_num < board_height - 1
 }
 
 impl Board {
     pub fn set_value(&mut self, player: Player, field: (usize, usize)) -> Result<(), &str> {
-        let height = self.get_height() - 1;
-        let width = self.get_width() - 1;
+        self.set_mark(player, player, field)
+    }
+
+    /// Sets `mark` on `field` for `mover`, who gets the line if the mark completes one.
+    /// Only in the wild variant does anybody set the other player's mark.
+    pub fn set_mark(
+        &mut self,
+        mover: Player,
+        mark: Player,
+        field: (usize, usize),
+    ) -> Result<(), &str> {
+        self.sync();
         let (x, y) = field;
-        if x > height || y > width {
+        if x >= self.grid.len() || y >= self.shape.cols {
             Err("Field must be in range of the grid")
-        } else if (*self)[x][y].is_some() {
+        } else if self.grid[x][y].is_some() {
             Err("Field has already been chosen. Please choose another field.")
+        } else if self.rules.gravity && self.drop_row(y) != Some(x) {
+            Err("Marks fall down, only the lowest empty cell of a column can be chosen.")
         } else {
-            (*self)[x][y] = Some(player);
+            let idx = x * self.shape.cols + y;
+            self.grid.cells[idx] = Some(mark);
+            self.bits[mark.index()].set(idx);
+            self.hash ^= zobrist(mark, idx);
+            self.empty -= 1;
+            if self.completed_by.is_none() && self.completes_line(field) {
+                self.completed_by = Some(mover);
+                self.completed_at = Some(field);
+            }
             Ok(())
         }
     }
 
+    /// Takes back the mark on `field`, returns whose it was.
+    pub fn clear_value(&mut self, field: (usize, usize)) -> Result<Player, &str> {
+        self.sync();
+        let (x, y) = field;
+        if x >= self.grid.len() || y >= self.shape.cols {
+            return Err("Field must be in range of the grid");
+        }
+        let idx = x * self.shape.cols + y;
+        match self.grid.cells[idx].take() {
+            Some(player) => {
+                self.bits[player.index()] = self.bits[player.index()].without(Bits::one(idx));
+                self.hash ^= zobrist(player, idx);
+                self.empty += 1;
+                // The game stays with whoever completed the line as long as it stands. Another
+                // line is only left if marks were set after the game was won, whose mover isn't
+                // known, so it goes to the owner of its marks.
+                let stands = self.completed_at.is_some_and(|at| self.completes_line(at));
+                if self.completed_by.is_some() && !stands {
+                    self.completed_by = self.get_winner();
+                    self.completed_at = None;
+                }
+                Ok(player)
+            }
+            None => Err("Field is empty, there is nothing to take back"),
+        }
+    }
+
+    /// The row a mark dropped into column `col` lands in, `None` if the column is full.
+    pub fn drop_row(&self, col: usize) -> Option<usize> {
+        if col >= self.shape.cols {
+            return None;
+        }
+        (0..self.shape.rows)
+            .rev()
+            .find(|&row| self.grid[row][col].is_none())
+    }
+
+    /// The cells a mark can be set on: every empty one, or with gravity the lowest empty
+    /// cell of each column.
+    pub fn playable_cells(&self) -> Vec<(usize, usize)> {
+        if self.rules.gravity {
+            (0..self.shape.cols)
+                .filter_map(|col| self.drop_row(col).map(|row| (row, col)))
+                .collect()
+        } else {
+            self.empty_cells().collect()
+        }
+    }
+
+    /// A 64 bit key of the marks on the board. Equal boards have equal keys.
+    pub fn hash(&self) -> u64 {
+        if !self.stale {
+            return self.hash;
+        }
+        let bits = self.bits();
+        [Player::ONE, Player::TWO]
+            .iter()
+            .flat_map(|&player| {
+                bits[player.index()]
+                    .ones()
+                    .map(move |idx| zobrist(player, idx))
+            })
+            .fold(0, |hash, key| hash ^ key)
+    }
+
+    /// The smallest key among the rotations and reflections of the board: all 8 on a square
+    /// board, the 4 that keep the rows and columns apart on any other. With gravity, down has
+    /// to stay down, which only leaves the mirror image. A cube also reads the same with its
+    /// layers turned upside down.
+    pub fn canonical_key(&self) -> u64 {
+        let planar = if self.rules.gravity {
+            2
+        } else if self.shape.rows == self.shape.cols {
+            8
+        } else {
+            4
+        };
+        let flips = if self.shape.layers > 1 { 2 } else { 1 };
+        let bits = self.bits();
+        (1..planar * flips)
+            .map(|symmetry| {
+                let mut key = 0;
+                for player in [Player::ONE, Player::TWO] {
+                    for idx in bits[player.index()].ones() {
+                        let moved =
+                            transform(self.shape, symmetry % planar, symmetry >= planar, idx);
+                        key ^= zobrist(player, moved);
+                    }
+                }
+                key
+            })
+            .fold(self.hash(), u64::min)
+    }
+
+    pub fn empty_cells(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let cols = self.shape.cols;
+        let bits = self.bits();
+        self.all
+            .without(bits[0].union(bits[1]))
+            .ones()
+            .map(move |idx| (idx / cols, idx % cols))
+    }
+
+    pub fn empty_count(&self) -> usize {
+        if self.stale {
+            self.grid.cells.iter().filter(|cell| cell.is_none()).count()
+        } else {
+            self.empty
+        }
+    }
+
+    /// Every run of cells that wins when one player holds all of it.
+    pub fn get_lines(&self) -> Vec<Vec<Option<Player>>> {
+        self.windows
+            .iter()
+            .map(|window| window.bits.ones().map(|idx| self.grid.cells[idx]).collect())
+            .collect()
+    }
+
     pub fn get_height(&self) -> usize {
-        (*self).len()
+        self.shape.rows
     }
 
     pub fn get_width(&self) -> usize {
-        (*self)[0].len()
+        self.shape.cols
     }
+
+    pub fn get_shape(&self) -> Shape {
+        self.shape
+    }
+
+    pub fn get_rules(&self) -> Rules {
+        self.rules
+    }
+}
+
+/// Where rotation or reflection number `symmetry` of every layer moves cell `idx`, with the
+/// layers in reverse order if `flip`. The first four keep rows as rows, the other four swap
+/// rows and columns and only work on square layers.
+fn transform(shape: Shape, symmetry: usize, flip: bool, idx: usize) -> usize {
+    let layer_cells = shape.rows * shape.cols;
+    let layer = match idx / layer_cells {
+        layer if flip => shape.layers - 1 - layer,
+        layer => layer,
+    };
+    let (row, col) = (idx % layer_cells / shape.cols, idx % shape.cols);
+    let (last_row, last_col) = (shape.rows - 1, shape.cols - 1);
+    let (row, col) = match symmetry {
+        0 => (row, col),
+        1 => (row, last_col - col),
+        2 => (last_row - row, col),
+        3 => (last_row - row, last_col - col),
+        4 => (col, row),
+        5 => (col, last_row - row),
+        6 => (last_col - col, row),
+        _ => (last_col - col, last_row - row),
+    };
+    shape.index((layer, row, col))
+}
+
+/// The random number for `player` holding cell `idx`, mixed from both with SplitMix64 so that
+/// every board size shares the same numbers without a table.
+fn zobrist(player: Player, idx: usize) -> u64 {
+    let mut z = (2 * idx + player.index() + 1) as u64;
+    z = z.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
 }
 
 #[cfg(test)]
@@ -153,6 +1025,7 @@ mod tests {
 
     use super::*;
 
+    /// The player holding every cell of `line`, if there is one.
     #[test]
     fn can_deref_and_use_inner_values() {
         let board = Board::default();
@@ -172,6 +1045,29 @@ mod tests {
         );
     }
 
+    #[test]
+    fn changes_in_place_are_seen_by_the_bits() {
+        let mut expected = Board::default();
+        let _ = expected.set_value(Player::ONE, (0, 0));
+        let _ = expected.set_value(Player::TWO, (1, 1));
+
+        let mut board = Board::default();
+        board[0][0] = Some(Player::ONE);
+        board[1][1] = Some(Player::TWO);
+        assert_eq!(7, board.empty_count());
+        assert_eq!(expected.hash(), board.hash());
+        assert_eq!(expected.canonical_key(), board.canonical_key());
+        assert!(!board.empty_cells().any(|cell| cell == (1, 1)));
+
+        // Moves after the change pick up from there
+        let _ = board.set_value(Player::ONE, (0, 1));
+        board[0][2] = Some(Player::ONE);
+        assert_eq!(BoardState::WON(Player::ONE), board.get_board_state());
+        assert_eq!(Ok(Player::ONE), board.clear_value((0, 2)));
+        assert_eq!(BoardState::ONGOING, board.get_board_state());
+        assert_eq!(6, board.empty_count());
+    }
+
     #[test]
     fn empty_line_gets_created() {
         let expected_line = "    |    |    ";
@@ -219,6 +1115,31 @@ mod tests {
         );
     }
 
+    #[test]
+    fn can_get_empty_cells() {
+        let mut board = Board::default();
+        assert_eq!(9, board.empty_cells().count());
+        let _ = board.set_value(Player::ONE, (0, 0));
+        let _ = board.set_value(Player::TWO, (1, 1));
+        let empty: Vec<_> = board.empty_cells().collect();
+        assert_eq!(7, empty.len());
+        assert!(!empty.contains(&(0, 0)));
+        assert!(!empty.contains(&(1, 1)));
+    }
+
+    #[test]
+    fn can_get_all_lines() {
+        let mut board = Board::default();
+        let _ = board.set_value(Player::ONE, (0, 0));
+        let lines = board.get_lines();
+        assert_eq!(8, lines.len());
+        let through_corner = lines
+            .iter()
+            .filter(|line| line.contains(&Some(Player::ONE)))
+            .count();
+        assert_eq!(3, through_corner);
+    }
+
     #[test]
     fn can_get_height() {
         let board = Board::default();
@@ -239,8 +1160,7 @@ mod tests {
         let _ = board.set_value(Player::ONE, (2, 2));
 
         let expected_tl_br = vec![Some(Player::ONE), Some(Player::ONE), Some(Player::ONE)];
-        let (tl_br, _) = board.get_diags();
-        assert_eq!(expected_tl_br, tl_br.collect::<Vec<_>>());
+        assert_eq!(expected_tl_br, board.get_lines()[6]);
 
         let mut board = Board::default();
         let _ = board.set_value(Player::TWO, (0, 2));
@@ -248,8 +1168,7 @@ mod tests {
         let _ = board.set_value(Player::TWO, (2, 0));
 
         let expected_bl_tr = vec![Some(Player::TWO), Some(Player::TWO), Some(Player::TWO)];
-        let (_, bl_tr) = board.get_diags();
-        assert_eq!(expected_bl_tr, bl_tr.collect::<Vec<_>>());
+        assert_eq!(expected_bl_tr, board.get_lines()[7]);
     }
 
     #[test]
@@ -275,11 +1194,7 @@ mod tests {
             vec![Some(Player::TWO), Some(Player::TWO), Some(Player::TWO)],
             vec![Some(Player::ONE), Some(Player::ONE), Some(Player::ONE)],
         ];
-        let rows: Vec<_> = board
-            .get_rows()
-            .map(|row| row.collect::<Vec<_>>())
-            .collect();
-        assert_eq!(expected_rows, rows);
+        assert_eq!(expected_rows, board.get_lines()[..3]);
     }
 
     #[test]
@@ -305,109 +1220,97 @@ mod tests {
             vec![Some(Player::TWO), Some(Player::TWO), Some(Player::TWO)],
             vec![Some(Player::ONE), Some(Player::ONE), Some(Player::ONE)],
         ];
-        let cols: Vec<_> = board
-            .get_cols()
-            .map(|col| col.collect::<Vec<_>>())
-            .collect::<Vec<_>>();
-        assert_eq!(expected_cols, cols);
+        assert_eq!(expected_cols, board.get_lines()[3..6]);
     }
 
     #[test]
     fn check_for_winner_finds_winner() {
-        let mut board = Board::default();
         // diag_tl_br (ONE is Winner)
+        let mut board = Board::default();
         let _ = board.set_value(Player::ONE, (0, 0));
         let _ = board.set_value(Player::ONE, (1, 1));
         let _ = board.set_value(Player::ONE, (2, 2));
-
-        let winner = check_for_winner(board.get_diags().0);
-        assert_eq!(Some(Player::ONE), winner);
+        assert_eq!(BoardState::WON(Player::ONE), board.get_board_state());
+        assert_eq!(Some(Player::ONE), board.get_winner());
 
         // diag_bl_tr (TWO is Winner)
         let mut board = Board::default();
         let _ = board.set_value(Player::TWO, (0, 2));
         let _ = board.set_value(Player::TWO, (1, 1));
         let _ = board.set_value(Player::TWO, (2, 0));
-
-        let winner = check_for_winner(board.get_diags().1);
-        assert_eq!(Some(Player::TWO), winner);
+        assert_eq!(BoardState::WON(Player::TWO), board.get_board_state());
+        assert_eq!(Some(Player::TWO), board.get_winner());
 
         // Nobody is winner anymore
         let mut board = Board::default();
         let _ = board.set_value(Player::ONE, (0, 2));
         let _ = board.set_value(Player::TWO, (0, 0));
-
-        let no_winner = check_for_winner(board.get_diags().0);
-        assert_eq!(None, no_winner);
-
-        let no_winner = check_for_winner(board.get_diags().1);
-        let _ = board.set_value(Player::ONE, (1, 2));
-        assert_eq!(None, no_winner);
+        let _ = board.set_value(Player::ONE, (1, 1));
+        let _ = board.set_value(Player::TWO, (2, 2));
+        assert_eq!(BoardState::ONGOING, board.get_board_state());
+        assert_eq!(None, board.get_winner());
     }
 
     #[test]
     fn line_that_contains_none_is_not_a_winner() {
-        let line = vec![Some(Player::ONE), Some(Player::ONE), None];
-        let no_winner = check_for_winner(line.into_iter());
-        assert_eq!(None, no_winner);
+        let mut board = Board::default();
+        let _ = board.set_value(Player::ONE, (0, 0));
+        let _ = board.set_value(Player::ONE, (0, 1));
+        assert_eq!(None, board.get_winner());
     }
 
     #[test]
     fn winner_in_row_gets_detected() {
-        let mut board = Board::default();
         // row-0
+        let mut board = Board::default();
         let _ = board.set_value(Player::ONE, (0, 0));
         let _ = board.set_value(Player::ONE, (0, 1));
         let _ = board.set_value(Player::ONE, (0, 2));
+        assert_eq!(BoardState::WON(Player::ONE), board.get_board_state());
+        assert_eq!(Some(Player::ONE), board.get_winner());
 
         // row-1
+        let mut board = Board::default();
         let _ = board.set_value(Player::TWO, (1, 0));
         let _ = board.set_value(Player::TWO, (1, 1));
         let _ = board.set_value(Player::TWO, (1, 2));
+        assert_eq!(BoardState::WON(Player::TWO), board.get_board_state());
+        assert_eq!(Some(Player::TWO), board.get_winner());
 
         // row-2
+        let mut board = Board::default();
         let _ = board.set_value(Player::ONE, (2, 0));
+        let _ = board.set_value(Player::TWO, (2, 1));
         let _ = board.set_value(Player::ONE, (2, 2));
-
-        let mut rows = board.get_rows();
-
-        let winner = check_for_winner(rows.next().unwrap());
-        assert_eq!(Some(Player::ONE), winner);
-
-        let winner = check_for_winner(rows.next().unwrap());
-        assert_eq!(Some(Player::TWO), winner);
-
-        let no_winner = check_for_winner(rows.next().unwrap());
-        assert_eq!(None, no_winner);
+        assert_eq!(BoardState::ONGOING, board.get_board_state());
+        assert_eq!(None, board.get_winner());
     }
 
     #[test]
     fn winner_in_cols_gets_detected() {
-        let mut board = Board::default();
         // col-0
+        let mut board = Board::default();
         let _ = board.set_value(Player::ONE, (0, 0));
         let _ = board.set_value(Player::ONE, (1, 0));
         let _ = board.set_value(Player::ONE, (2, 0));
+        assert_eq!(BoardState::WON(Player::ONE), board.get_board_state());
+        assert_eq!(Some(Player::ONE), board.get_winner());
 
         // col-1
+        let mut board = Board::default();
         let _ = board.set_value(Player::TWO, (0, 1));
         let _ = board.set_value(Player::TWO, (1, 1));
         let _ = board.set_value(Player::TWO, (2, 1));
+        assert_eq!(BoardState::WON(Player::TWO), board.get_board_state());
+        assert_eq!(Some(Player::TWO), board.get_winner());
 
         // col-2
+        let mut board = Board::default();
         let _ = board.set_value(Player::ONE, (0, 2));
         let _ = board.set_value(Player::ONE, (1, 2));
-
-        let mut columns = board.get_cols();
-
-        let winner = check_for_winner(columns.next().unwrap());
-        assert_eq!(Some(Player::ONE), winner);
-
-        let winner = check_for_winner(columns.next().unwrap());
-        assert_eq!(Some(Player::TWO), winner);
-
-        let no_winner = check_for_winner(columns.next().unwrap());
-        assert_eq!(None, no_winner);
+        let _ = board.set_value(Player::TWO, (2, 2));
+        assert_eq!(BoardState::ONGOING, board.get_board_state());
+        assert_eq!(None, board.get_winner());
     }
 
     #[test]
@@ -511,4 +1414,333 @@ mod tests {
         let winner_one = board.get_board_state();
         assert_eq!(BoardState::WON(Player::ONE), winner_one);
     }
+
+    #[test]
+    fn shapes_can_be_parsed() {
+        assert_eq!(Ok(Shape::default()), "3x3".parse());
+        let shape: Shape = "6x7x4".parse().unwrap();
+        assert_eq!((6, 7, 4), (shape.rows, shape.cols, shape.win_length));
+        assert_eq!("6x7x4", shape.to_string());
+        assert!("3x3x4".parse::<Shape>().is_err());
+        assert_eq!(Ok(Shape::cube(4)), "4x4x4x4".parse());
+        assert_eq!("4x4x4x4", Shape::cube(4).to_string());
+        let shape: Shape = "5x3x3x4".parse().unwrap();
+        assert_eq!(Ok(shape), shape.to_string().parse());
+        assert!("3x3x3x4".parse::<Shape>().is_err());
+        assert!("17x17".parse::<Shape>().is_err());
+        assert!("0x3".parse::<Shape>().is_err());
+        assert!("big".parse::<Shape>().is_err());
+    }
+
+    #[test]
+    fn larger_boards_win_with_win_length_in_a_row() {
+        let mut board = Board::new("7x7x5".parse().unwrap());
+        assert_eq!(49, board.empty_cells().count());
+        // 3 rows, 3 cols, 3x3 windows in both diagonal directions
+        assert_eq!(7 * 3 * 2 + 3 * 3 * 2, board.get_lines().len());
+        for col in 2..6 {
+            let _ = board.set_value(Player::TWO, (6 - col, col));
+        }
+        assert_eq!(BoardState::ONGOING, board.get_board_state());
+        let _ = board.set_value(Player::TWO, (0, 6));
+        assert_eq!(BoardState::WON(Player::TWO), board.get_board_state());
+        assert!(board.set_value(Player::ONE, (7, 0)).is_err());
+        assert_eq!(Some(Player::TWO), board[4][2]);
+    }
+
+    #[test]
+    fn rectangular_boards_fill_up() {
+        let mut board = Board::new("1x4x3".parse().unwrap());
+        for (col, player) in [Player::ONE, Player::TWO, Player::ONE, Player::ONE]
+            .iter()
+            .enumerate()
+        {
+            let _ = board.set_value(*player, (0, col));
+        }
+        assert_eq!(BoardState::DRAW, board.get_board_state());
+        assert_eq!(0, board.empty_cells().count());
+    }
+
+    #[test]
+    fn board_state_tells_why_the_game_ended() {
+        assert_eq!(None, BoardState::ONGOING.result());
+        let won = BoardState::WON(Player::TWO).result().unwrap();
+        assert_eq!((Some(Player::TWO), Reason::LINE), (won.winner, won.reason));
+        let draw = BoardState::DRAW.result().unwrap();
+        assert_eq!((None, Reason::FULL), (draw.winner, draw.reason));
+        let resigned = BoardState::ENDED(Some(Player::ONE), Reason::RESIGNATION)
+            .result()
+            .unwrap();
+        assert_eq!("1-0 (resignation)", resigned.to_string());
+        let agreed = BoardState::ENDED(None, Reason::AGREEMENT).result().unwrap();
+        assert_eq!("½-½ (agreed draw)", agreed.to_string());
+    }
+
+    #[test]
+    fn reason_can_be_parsed_from_its_description() {
+        assert_eq!(Ok(Reason::TIMEOUT), "timeout".parse());
+        assert_eq!(Ok(Reason::LINE), "line completed".parse());
+        assert!("boredom".parse::<Reason>().is_err());
+    }
+
+    #[test]
+    fn hash_follows_set_and_cleared_marks() {
+        let mut board = Board::default();
+        let _ = board.set_value(Player::ONE, (0, 0));
+        let _ = board.set_value(Player::TWO, (1, 1));
+        let hash = board.hash();
+        assert_ne!(0, hash);
+        let _ = board.set_value(Player::ONE, (2, 1));
+        assert_eq!(Ok(Player::ONE), board.clear_value((2, 1)));
+        assert_eq!(hash, board.hash());
+        assert_eq!(None, board[2][1]);
+        assert!(board.clear_value((2, 1)).is_err());
+
+        // The same marks in another order give the same key
+        let mut other = Board::default();
+        let _ = other.set_value(Player::TWO, (1, 1));
+        let _ = other.set_value(Player::ONE, (0, 0));
+        assert_eq!(hash, other.hash());
+        let _ = board.clear_value((0, 0));
+        let _ = board.clear_value((1, 1));
+        assert_eq!(0, board.hash());
+        assert_eq!(9, board.empty_cells().count());
+    }
+
+    #[test]
+    fn rotations_and_reflections_share_a_canonical_key() {
+        let board = |marks: &[(Player, (usize, usize))]| {
+            let mut board = Board::default();
+            for &(player, field) in marks {
+                let _ = board.set_value(player, field);
+            }
+            board
+        };
+        let original = board(&[(Player::ONE, (0, 0)), (Player::TWO, (0, 1))]);
+        let turned = board(&[(Player::ONE, (0, 2)), (Player::TWO, (1, 2))]);
+        let mirrored = board(&[(Player::ONE, (0, 0)), (Player::TWO, (1, 0))]);
+        assert_ne!(original.hash(), turned.hash());
+        assert_eq!(original.canonical_key(), turned.canonical_key());
+        assert_eq!(original.canonical_key(), mirrored.canonical_key());
+        let different = board(&[(Player::ONE, (0, 0)), (Player::TWO, (1, 1))]);
+        assert_ne!(original.canonical_key(), different.canonical_key());
+        let swapped = board(&[(Player::TWO, (0, 0)), (Player::ONE, (0, 1))]);
+        assert_ne!(original.canonical_key(), swapped.canonical_key());
+    }
+
+    #[test]
+    fn rectangular_boards_are_not_transposed() {
+        let shape = "2x3".parse().unwrap();
+        let mut left = Board::new(shape);
+        let _ = left.set_value(Player::ONE, (0, 0));
+        let mut right = Board::new(shape);
+        let _ = right.set_value(Player::ONE, (1, 2));
+        assert_eq!(left.canonical_key(), right.canonical_key());
+        let mut middle = Board::new(shape);
+        let _ = middle.set_value(Player::ONE, (0, 1));
+        assert_ne!(left.canonical_key(), middle.canonical_key());
+    }
+
+    #[test]
+    fn last_move_decides_the_winner() {
+        let mut board = Board::new("15x15x5".parse().unwrap());
+        // A diagonal from 3,7 down to the left, completed in the middle
+        for field in [(3, 7), (4, 6), (6, 4), (7, 3)] {
+            let _ = board.set_value(Player::TWO, field);
+            assert!(!board.completes_line(field));
+        }
+        assert_eq!(BoardState::ONGOING, board.get_board_state());
+        let _ = board.set_value(Player::TWO, (5, 5));
+        assert!(board.completes_line((5, 5)));
+        assert!(board.completes_line((7, 3)));
+        assert_eq!(BoardState::WON(Player::TWO), board.get_board_state());
+        assert_eq!(Ok(Player::TWO), board.clear_value((3, 7)));
+        assert_eq!(BoardState::ONGOING, board.get_board_state());
+        assert!(!board.completes_line((0, 0)));
+    }
+
+    #[test]
+    fn empty_cells_are_counted_as_they_go() {
+        let mut board = Board::default();
+        assert_eq!(9, board.empty_count());
+        let _ = board.set_value(Player::ONE, (1, 1));
+        let _ = board.set_value(Player::ONE, (1, 1));
+        assert_eq!(8, board.empty_count());
+        let _ = board.clear_value((1, 1));
+        let _ = board.clear_value((1, 1));
+        assert_eq!(9, board.empty_count());
+    }
+
+    #[test]
+    fn marks_fall_down_with_gravity() {
+        let rules = Rules {
+            gravity: true,
+            ..Rules::default()
+        };
+        let mut board = Board::new("6x7x4".parse().unwrap()).with_rules(rules);
+        assert_eq!(Some(5), board.drop_row(3));
+        assert!(board.set_value(Player::ONE, (0, 3)).is_err());
+        assert_eq!(Ok(()), board.set_value(Player::ONE, (5, 3)));
+        assert_eq!(Some(4), board.drop_row(3));
+        assert_eq!(None, board.drop_row(7));
+        let playable = board.playable_cells();
+        assert_eq!(7, playable.len());
+        assert!(playable.contains(&(4, 3)) && playable.contains(&(5, 0)));
+        for row in (0..5).rev() {
+            let _ = board.set_value(Player::TWO, (row, 3));
+        }
+        assert_eq!(None, board.drop_row(3));
+        assert_eq!(6, board.playable_cells().len());
+        assert_eq!(BoardState::WON(Player::TWO), board.get_board_state());
+    }
+
+    #[test]
+    fn gravity_keeps_only_the_mirror_image() {
+        let rules = Rules {
+            gravity: true,
+            ..Rules::default()
+        };
+        let shape = "4x4x3".parse().unwrap();
+        let board = |field| {
+            let mut board = Board::new(shape).with_rules(rules);
+            let _ = board.set_value(Player::ONE, field);
+            board.canonical_key()
+        };
+        assert_eq!(board((3, 0)), board((3, 3)));
+        assert_ne!(board((3, 0)), board((3, 1)));
+    }
+
+    #[test]
+    fn completing_a_line_loses_in_misere() {
+        let rules = Rules {
+            misere: true,
+            ..Rules::default()
+        };
+        let mut board = Board::default().with_rules(rules);
+        for col in 0..3 {
+            let _ = board.set_value(Player::ONE, (0, col));
+        }
+        assert_eq!(BoardState::WON(Player::TWO), board.get_board_state());
+        let _ = board.clear_value((0, 2));
+        assert_eq!(BoardState::ONGOING, board.get_board_state());
+    }
+
+    #[test]
+    fn line_of_any_mark_goes_to_its_mover() {
+        let mut board = Board::default();
+        let _ = board.set_mark(Player::TWO, Player::ONE, (1, 0));
+        let _ = board.set_mark(Player::ONE, Player::ONE, (1, 1));
+        assert_eq!(Some(Player::ONE), board[1][0]);
+        assert_eq!(BoardState::ONGOING, board.get_board_state());
+        let _ = board.set_mark(Player::TWO, Player::ONE, (1, 2));
+        assert_eq!(BoardState::WON(Player::TWO), board.get_board_state());
+        assert!(board.set_mark(Player::ONE, Player::TWO, (1, 2)).is_err());
+    }
+
+    #[test]
+    fn taking_back_keeps_a_wild_line_with_its_mover() {
+        for misere in [false, true] {
+            let rules = Rules {
+                wild: true,
+                misere,
+                ..Rules::default()
+            };
+            let mut board = Board::default().with_rules(rules);
+            let _ = board.set_mark(Player::ONE, Player::TWO, (2, 2));
+            let _ = board.set_mark(Player::ONE, Player::ONE, (1, 0));
+            let _ = board.set_mark(Player::ONE, Player::ONE, (1, 1));
+            // TWO completes the row of X marks
+            let _ = board.set_mark(Player::TWO, Player::ONE, (1, 2));
+            let won = board.get_board_state();
+            assert_eq!(
+                BoardState::WON(if misere { Player::ONE } else { Player::TWO }),
+                won
+            );
+
+            // Taking back a mark off the line leaves the game won by the same player
+            assert_eq!(Ok(Player::TWO), board.clear_value((2, 2)));
+            assert_eq!(won, board.get_board_state());
+
+            assert_eq!(Ok(Player::ONE), board.clear_value((1, 2)));
+            assert_eq!(BoardState::ONGOING, board.get_board_state());
+        }
+    }
+
+    #[test]
+    fn lines_of_a_cube_run_through_the_layers() {
+        let shape = Shape::cube(4);
+        let mut board = Board::new(shape);
+        assert_eq!(76, board.get_lines().len());
+        // A space diagonal, from the top left of the first layer to the bottom right of the last
+        for step in 0..3 {
+            let _ = board.set_value(Player::ONE, shape.field(step, step, step));
+        }
+        assert_eq!(BoardState::ONGOING, board.get_board_state());
+        let _ = board.set_value(Player::ONE, shape.field(3, 3, 3));
+        assert_eq!(BoardState::WON(Player::ONE), board.get_board_state());
+        assert_eq!(Some(Player::ONE), board.get_winner());
+
+        let mut board = Board::new(shape);
+        for layer in 0..4 {
+            let _ = board.set_value(Player::TWO, shape.field(layer, 2, 1));
+        }
+        assert!(board.completes_line(shape.field(1, 2, 1)));
+        assert_eq!(Some(Player::TWO), board.get_winner());
+        assert!(board.set_value(Player::ONE, shape.field(4, 0, 0)).is_err());
+    }
+
+    #[test]
+    fn cubes_turned_upside_down_share_a_canonical_key() {
+        let shape = Shape::cube(4);
+        let keys: Vec<u64> = [0, 3, 1]
+            .iter()
+            .map(|&layer| {
+                let mut board = Board::new(shape);
+                let _ = board.set_value(Player::ONE, shape.field(layer, 1, 2));
+                board.canonical_key()
+            })
+            .collect();
+        assert_eq!(keys[0], keys[1]);
+        assert_ne!(keys[0], keys[2]);
+    }
+
+    #[test]
+    fn lines_cross_the_edges_of_a_wrapped_board() {
+        let rules = Rules {
+            wrap: true,
+            ..Rules::default()
+        };
+        let shape = "5x5x3".parse().unwrap();
+        assert_eq!(48, Board::new(shape).get_lines().len());
+        let mut board = Board::new(shape).with_rules(rules);
+        assert_eq!(100, board.get_lines().len());
+        for col in [3, 4, 0] {
+            let _ = board.set_value(Player::ONE, (1, col));
+        }
+        assert_eq!(BoardState::WON(Player::ONE), board.get_board_state());
+        assert_eq!(Some(Player::ONE), board.get_winner());
+        // A broken diagonal: down and to the left, on into the last column
+        let mut board = Board::new(shape).with_rules(rules);
+        for field in [(0, 1), (1, 0), (2, 4)] {
+            let _ = board.set_value(Player::TWO, field);
+        }
+        assert_eq!(BoardState::WON(Player::TWO), board.get_board_state());
+    }
+
+    #[test]
+    fn wrapped_lines_need_as_many_different_cells() {
+        let rules = Rules {
+            wrap: true,
+            ..Rules::default()
+        };
+        // The columns are only three cells long, going around one doesn't make five
+        let mut board = Board::new("3x5x5".parse().unwrap()).with_rules(rules);
+        for row in 0..3 {
+            let _ = board.set_value(Player::ONE, (row, 0));
+        }
+        assert_eq!(BoardState::ONGOING, board.get_board_state());
+        assert_eq!(None, board.get_winner());
+        // Three rows, and the diagonals run through all fifteen cells
+        assert_eq!(3 + 2 * 15, board.get_lines().len());
+    }
 }
@@ -0,0 +1,190 @@
+use crate::game::player::Player;
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TimeControl {
+    /// One budget for the whole game.
+    SUDDEN(Duration),
+    /// A starting budget plus an increment after every move.
+    FISCHER(Duration, Duration),
+    /// A fixed budget per move that does not carry over.
+    MOVE(Duration),
+}
+
+impl TimeControl {
+    fn budget(&self) -> Duration {
+        match *self {
+            Self::SUDDEN(budget) | Self::FISCHER(budget, _) | Self::MOVE(budget) => budget,
+        }
+    }
+}
+
+/// Parses `sudden:<secs>`, `fischer:<secs>+<secs>` and `move:<secs>`.
+impl FromStr for TimeControl {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let secs = |value: &str| {
+            value
+                .parse::<u64>()
+                .map(Duration::from_secs)
+                .map_err(|_| format!("'{}' is not a number of seconds", value))
+        };
+        match s.split_once(':') {
+            Some(("sudden", budget)) => Ok(Self::SUDDEN(secs(budget)?)),
+            Some(("move", budget)) => Ok(Self::MOVE(secs(budget)?)),
+            Some(("fischer", rest)) => match rest.split_once('+') {
+                Some((budget, increment)) => Ok(Self::FISCHER(secs(budget)?, secs(increment)?)),
+                None => Err("Fischer needs an increment, e.g. fischer:300+5".to_string()),
+            },
+            _ => Err(format!(
+                "Unknown time control '{}'. Use sudden:<secs>, fischer:<secs>+<secs> or move:<secs>",
+                s
+            )),
+        }
+    }
+}
+
+/// A chess clock for both players. Only the clock of the player to move is running.
+#[derive(Debug)]
+pub struct Clock {
+    control: TimeControl,
+    remaining: [Duration; 2],
+    running: Option<(Player, Instant)>,
+}
+
+impl Clock {
+    pub fn new(control: TimeControl) -> Self {
+        let budget = control.budget();
+        Clock {
+            control,
+            remaining: [budget, budget],
+            running: None,
+        }
+    }
+
+    /// Starts the clock of `player`. Does nothing if it is already running.
+    pub fn start(&mut self, player: Player, now: Instant) {
+        if self.running.is_none() {
+            self.running = Some((player, now));
+        }
+    }
+
+    pub fn remaining(&self, player: Player, now: Instant) -> Duration {
+        let left = self.remaining[player.index()];
+        match self.running {
+            Some((running, since)) if running == player => {
+                left.saturating_sub(now.saturating_duration_since(since))
+            }
+            _ => left,
+        }
+    }
+
+    pub fn is_flagged(&self, player: Player, now: Instant) -> bool {
+        self.remaining(player, now) == Duration::ZERO
+    }
+
+    /// Stops the running clock after a move and applies the time control.
+    pub fn punch(&mut self, now: Instant) {
+        if let Some((player, _)) = self.running {
+            let left = self.remaining(player, now);
+            self.remaining[player.index()] = match self.control {
+                TimeControl::SUDDEN(_) => left,
+                TimeControl::FISCHER(_, increment) => left + increment,
+                TimeControl::MOVE(budget) => budget,
+            };
+            self.running = None;
+        }
+    }
+}
+
+/// Formats a remaining time as `m:ss`, rounding started seconds up.
+pub struct Remaining(pub Duration);
+
+impl fmt::Display for Remaining {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let secs = self.0.as_secs() + u64::from(self.0.subsec_nanos() > 0);
+        write!(f, "{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    const MIN: Duration = Duration::from_secs(60);
+    const SEC: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn can_parse_time_controls() {
+        assert_eq!(Ok(TimeControl::SUDDEN(5 * MIN)), "sudden:300".parse());
+        assert_eq!(
+            Ok(TimeControl::FISCHER(MIN, 2 * SEC)),
+            "fischer:60+2".parse()
+        );
+        assert_eq!(Ok(TimeControl::MOVE(30 * SEC)), "move:30".parse());
+        assert!("fischer:60".parse::<TimeControl>().is_err());
+        assert!("move:soon".parse::<TimeControl>().is_err());
+        assert!("blitz".parse::<TimeControl>().is_err());
+    }
+
+    #[test]
+    fn only_the_running_clock_ticks() {
+        let mut clock = Clock::new(TimeControl::SUDDEN(MIN));
+        let start = Instant::now();
+        clock.start(Player::ONE, start);
+        let later = start + 10 * SEC;
+        assert_eq!(50 * SEC, clock.remaining(Player::ONE, later));
+        assert_eq!(MIN, clock.remaining(Player::TWO, later));
+    }
+
+    #[test]
+    fn starting_twice_does_not_reset_the_clock() {
+        let mut clock = Clock::new(TimeControl::SUDDEN(MIN));
+        let start = Instant::now();
+        clock.start(Player::ONE, start);
+        clock.start(Player::ONE, start + 10 * SEC);
+        assert_eq!(40 * SEC, clock.remaining(Player::ONE, start + 20 * SEC));
+    }
+
+    #[test]
+    fn punching_applies_the_time_control() {
+        let start = Instant::now();
+        let after_move = start + 10 * SEC;
+
+        let mut sudden = Clock::new(TimeControl::SUDDEN(MIN));
+        sudden.start(Player::ONE, start);
+        sudden.punch(after_move);
+        assert_eq!(50 * SEC, sudden.remaining(Player::ONE, after_move + MIN));
+
+        let mut fischer = Clock::new(TimeControl::FISCHER(MIN, 5 * SEC));
+        fischer.start(Player::ONE, start);
+        fischer.punch(after_move);
+        assert_eq!(55 * SEC, fischer.remaining(Player::ONE, after_move));
+
+        let mut per_move = Clock::new(TimeControl::MOVE(MIN));
+        per_move.start(Player::ONE, start);
+        per_move.punch(after_move);
+        assert_eq!(MIN, per_move.remaining(Player::ONE, after_move));
+    }
+
+    #[test]
+    fn clock_runs_out() {
+        let mut clock = Clock::new(TimeControl::MOVE(30 * SEC));
+        let start = Instant::now();
+        clock.start(Player::TWO, start);
+        assert!(!clock.is_flagged(Player::TWO, start + 29 * SEC));
+        assert!(clock.is_flagged(Player::TWO, start + 31 * SEC));
+    }
+
+    #[test]
+    fn remaining_time_is_formatted_as_minutes_and_seconds() {
+        assert_eq!("4:05", Remaining(4 * MIN + 5 * SEC).to_string());
+        assert_eq!("0:01", Remaining(Duration::from_millis(200)).to_string());
+        assert_eq!("0:00", Remaining(Duration::ZERO).to_string());
+    }
+}
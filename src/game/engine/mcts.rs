@@ -0,0 +1,107 @@
+use crate::game::engine::rng::Rng;
+use crate::game::engine::Position;
+use crate::game::player::Player;
+
+/// Balances trying promising moves again against trying rarely visited ones.
+const EXPLORATION: f64 = std::f64::consts::SQRT_2;
+
+struct Node<M> {
+    mv: Option<M>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    untried: Vec<M>,
+    /// The player who made `mv`. Rewards are counted from this player's view.
+    mover: Player,
+    visits: u32,
+    reward: f64,
+}
+
+/// Runs `playouts` random games and returns the move that was explored the most.
+pub fn search<P: Position>(position: &P, playouts: usize, rng: &mut Rng) -> Option<P::Move> {
+    let mut nodes = vec![Node {
+        mv: None,
+        parent: None,
+        children: Vec::new(),
+        untried: position.legal_moves(),
+        mover: position.to_move().other(),
+        visits: 0,
+        reward: 0.0,
+    }];
+
+    for _ in 0..playouts {
+        let mut current = position.clone();
+        let mut idx = 0;
+
+        // Selection: follow the best children of fully expanded nodes
+        while nodes[idx].untried.is_empty() && !nodes[idx].children.is_empty() {
+            idx = best_child(&nodes, idx);
+            if let Some(mv) = nodes[idx].mv {
+                current.play(mv);
+            }
+        }
+
+        // Expansion: add one untried move
+        if !nodes[idx].untried.is_empty() {
+            let pick = rng.below(nodes[idx].untried.len());
+            let mv = nodes[idx].untried.swap_remove(pick);
+            let mover = current.to_move();
+            current.play(mv);
+            nodes.push(Node {
+                mv: Some(mv),
+                parent: Some(idx),
+                children: Vec::new(),
+                untried: current.legal_moves(),
+                mover,
+                visits: 0,
+                reward: 0.0,
+            });
+            let child = nodes.len() - 1;
+            nodes[idx].children.push(child);
+            idx = child;
+        }
+
+        // Simulation: random moves until the game is over
+        loop {
+            let moves = current.legal_moves();
+            match rng.pick(&moves) {
+                Some(&mv) => current.play(mv),
+                None => break,
+            }
+        }
+        let winner = current.state().result().and_then(|result| result.winner);
+
+        // Backpropagation
+        let mut next = Some(idx);
+        while let Some(node_idx) = next {
+            let node = &mut nodes[node_idx];
+            node.visits += 1;
+            node.reward += match winner {
+                Some(winner) if winner == node.mover => 1.0,
+                Some(_) => 0.0,
+                None => 0.5,
+            };
+            next = node.parent;
+        }
+    }
+
+    nodes[0]
+        .children
+        .iter()
+        .max_by_key(|&&child| nodes[child].visits)
+        .and_then(|&child| nodes[child].mv)
+        .or_else(|| position.legal_moves().first().copied())
+}
+
+fn best_child<M>(nodes: &[Node<M>], idx: usize) -> usize {
+    let parent_visits = f64::from(nodes[idx].visits).ln();
+    let uct = |child: usize| {
+        let node = &nodes[child];
+        let visits = f64::from(node.visits);
+        node.reward / visits + EXPLORATION * (parent_visits / visits).sqrt()
+    };
+    *nodes[idx]
+        .children
+        .iter()
+        .max_by(|&&a, &&b| uct(a).total_cmp(&uct(b)))
+        .expect("Only nodes with children get selected")
+}
@@ -0,0 +1,299 @@
+use crate::game::board::BoardState;
+use crate::game::engine::Position;
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicI32, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// The score of a won position. Faster wins score higher.
+pub const WIN: i32 = 1_000_000;
+const INFINITY: i32 = WIN + 1;
+/// Scores beyond this are wins or losses, counted from the root of the search.
+const DECIDED: i32 = WIN / 2;
+
+/// What a search found out about a position it has seen before.
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    depth: usize,
+    /// Wins and losses are counted from the position, not from the root.
+    score: i32,
+    bound: Bound,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Bound {
+    EXACT,
+    /// The search was cut off, the score is at least this.
+    LOWER,
+    /// No move reached alpha, the score is at most this.
+    UPPER,
+}
+
+/// Positions already searched, by `Position::key`. Split into shards behind their own locks,
+/// so threads searching at the same time rarely wait for each other.
+struct Table {
+    shards: Vec<Mutex<HashMap<u64, Entry>>>,
+}
+
+const SHARDS: usize = 64;
+
+impl Table {
+    fn new() -> Self {
+        Table {
+            shards: (0..SHARDS).map(|_| Mutex::new(HashMap::new())).collect(),
+        }
+    }
+
+    fn shard(&self, key: u64) -> std::sync::MutexGuard<'_, HashMap<u64, Entry>> {
+        self.shards[key as usize % SHARDS]
+            .lock()
+            .expect("No search panics while holding the lock")
+    }
+
+    fn get(&self, key: u64) -> Option<Entry> {
+        self.shard(key).get(&key).copied()
+    }
+
+    fn insert(&self, key: u64, entry: Entry) {
+        self.shard(key).insert(key, entry);
+    }
+}
+
+/// The value of `position` for the player to move, searched `depth` moves deep.
+fn negamax<P: Position>(
+    position: &P,
+    depth: usize,
+    ply: i32,
+    alpha: i32,
+    beta: i32,
+    table: &Table,
+) -> i32 {
+    if let Some(score) = terminal_score(position, ply) {
+        return score;
+    }
+    if depth == 0 {
+        return position.heuristic(position.to_move());
+    }
+    let key = position.key();
+    if let Some(entry) = key.and_then(|key| table.get(key)) {
+        if entry.depth >= depth {
+            let score = from_table(entry.score, ply);
+            match entry.bound {
+                Bound::EXACT => return score,
+                Bound::LOWER if score >= beta => return score,
+                Bound::UPPER if score <= alpha => return score,
+                _ => {}
+            }
+        }
+    }
+    let mut best = alpha;
+    let mut bound = Bound::UPPER;
+    for mv in position.legal_moves() {
+        let mut next = position.clone();
+        next.play(mv);
+        let score = -negamax(&next, depth - 1, ply + 1, -beta, -best, table);
+        if score >= beta {
+            best = score;
+            bound = Bound::LOWER;
+            break;
+        }
+        if score > best {
+            best = score;
+            bound = Bound::EXACT;
+        }
+    }
+    if let Some(key) = key {
+        let score = to_table(best, ply);
+        table.insert(
+            key,
+            Entry {
+                depth,
+                score,
+                bound,
+            },
+        );
+    }
+    best
+}
+
+/// Makes a score found `ply` moves from the root relative to the position itself, for the table.
+fn to_table(score: i32, ply: i32) -> i32 {
+    match score {
+        score if score > DECIDED => score + ply,
+        score if score < -DECIDED => score - ply,
+        score => score,
+    }
+}
+
+/// Turns a score from the table back into one counted from the root, `ply` moves away.
+fn from_table(score: i32, ply: i32) -> i32 {
+    match score {
+        score if score > DECIDED => score - ply,
+        score if score < -DECIDED => score + ply,
+        score => score,
+    }
+}
+
+/// Scores a finished game from the view of the player to move.
+pub fn terminal_score<P: Position>(position: &P, ply: i32) -> Option<i32> {
+    match position.state() {
+        BoardState::ONGOING => None,
+        state => Some(match state.result().and_then(|result| result.winner) {
+            Some(winner) if winner == position.to_move() => WIN - ply,
+            Some(_) => -(WIN - ply),
+            None => 0,
+        }),
+    }
+}
+
+/// The exact value of every legal move for the player to move.
+pub fn move_values<P: Position>(position: &P, depth: usize) -> Vec<(P::Move, i32)> {
+    if let Some(values) = position.solved(depth) {
+        return values;
+    }
+    search_root(position, depth, 1, false)
+}
+
+/// The best value and every move that reaches it, searched on `threads` threads.
+pub fn best_moves<P: Position>(position: &P, depth: usize, threads: usize) -> (i32, Vec<P::Move>) {
+    let values = match position.solved(depth) {
+        Some(values) => values,
+        None => search_root(position, depth, threads, true),
+    };
+    let best = values
+        .iter()
+        .map(|(_, score)| *score)
+        .max()
+        .unwrap_or(-INFINITY);
+    let moves = values
+        .into_iter()
+        .filter(|(_, score)| *score == best)
+        .map(|(mv, _)| mv)
+        .collect();
+    (best, moves)
+}
+
+/// Scores the legal moves in their order. The threads take the next move that nobody searches
+/// yet and share one table. With `narrow`, only the best moves get exact scores: the others are
+/// searched with a window just below the best score so far, which is all it takes to tell
+/// them apart, and which gives the same best moves however the threads finish.
+fn search_root<P: Position>(
+    position: &P,
+    depth: usize,
+    threads: usize,
+    narrow: bool,
+) -> Vec<(P::Move, i32)> {
+    let moves = position.legal_moves();
+    let table = Table::new();
+    let next_move = AtomicUsize::new(0);
+    let best = AtomicI32::new(-INFINITY);
+    let scores = Mutex::new(vec![-INFINITY; moves.len()]);
+
+    thread::scope(|scope| {
+        for _ in 0..threads.clamp(1, moves.len().max(1)) {
+            scope.spawn(|| loop {
+                let idx = next_move.fetch_add(1, Ordering::Relaxed);
+                let mv = match moves.get(idx) {
+                    Some(&mv) => mv,
+                    None => break,
+                };
+                let beta = if narrow {
+                    -(best.load(Ordering::Relaxed) - 1)
+                } else {
+                    INFINITY
+                };
+                let mut next = position.clone();
+                next.play(mv);
+                let score = -negamax(&next, depth - 1, 1, -INFINITY, beta, &table);
+                best.fetch_max(score, Ordering::Relaxed);
+                scores
+                    .lock()
+                    .expect("No search panics while holding the lock")[idx] = score;
+            });
+        }
+    });
+
+    let scores = scores.into_inner().expect("All threads are done");
+    moves.into_iter().zip(scores).collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::game::board::Rules;
+    use crate::game::engine::Turn;
+    use crate::game::player::Player;
+
+    #[test]
+    fn empty_board_is_a_draw_and_every_move_keeps_it() {
+        let (value, moves) = best_moves(&Turn::default(), 9, 1);
+        assert_eq!(0, value);
+        assert_eq!(9, moves.len());
+    }
+
+    #[test]
+    fn move_values_tell_good_from_bad_moves() {
+        let mut turn = Turn::default();
+        turn.play((0, 0));
+        // Only the centre keeps the draw for TWO after a corner opening
+        let values = move_values(&turn, 8);
+        assert_eq!(8, values.len());
+        let drawing: Vec<_> = values
+            .iter()
+            .filter(|(_, value)| *value == 0)
+            .map(|(mv, _)| *mv)
+            .collect();
+        assert_eq!(vec![(1, 1)], drawing);
+        assert!(values.iter().all(|(_, value)| *value <= 0));
+    }
+
+    #[test]
+    fn tablebase_agrees_with_the_search() {
+        let mut turn = Turn::default();
+        for field in [(0, 0), (0, 1), (2, 2)] {
+            let searched: Vec<_> = turn
+                .legal_moves()
+                .into_iter()
+                .map(|mv| {
+                    let mut next = turn.clone();
+                    next.play(mv);
+                    (
+                        mv,
+                        -negamax(&next, 8, 1, -INFINITY, INFINITY, &Table::new()),
+                    )
+                })
+                .collect();
+            assert_eq!(Some(searched), turn.solved(9));
+            turn.play(field);
+        }
+        assert_eq!(None, turn.solved(2));
+    }
+
+    #[test]
+    fn finds_the_fastest_win() {
+        let mut turn = Turn::default();
+        // ONE: corners 0,0 and 2,2, TWO: 0,2 and 2,0 -> ONE wins with the centre
+        turn.play((0, 0));
+        turn.play((0, 2));
+        turn.play((2, 2));
+        turn.play((2, 0));
+        let (value, moves) = best_moves(&turn, 9, 1);
+        assert_eq!(WIN - 1, value);
+        assert_eq!(vec![(1, 1)], moves);
+        assert_eq!(Player::ONE, turn.to_move());
+    }
+
+    #[test]
+    fn threads_find_the_same_moves() {
+        let mut turn = Turn::new("5x5x4".parse().unwrap(), Rules::default());
+        turn.play((2, 2));
+        turn.play((1, 1));
+        let single = best_moves(&turn, 3, 1);
+        for threads in [2, 3, 8] {
+            assert_eq!(single, best_moves(&turn, 3, threads));
+        }
+        assert!(!single.1.is_empty());
+    }
+}
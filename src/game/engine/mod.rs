@@ -0,0 +1,504 @@
+mod mcts;
+mod minimax;
+pub mod rng;
+
+use crate::game::board::{Board, BoardState, Rules, Shape};
+use crate::game::player::Player;
+use crate::game::tablebase;
+use rng::Rng;
+
+pub use minimax::{move_values, WIN};
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Everything a search needs to know about a game.
+/// Variants implement it once and get every strategy for free.
+/// Searches share positions between threads, so they have to be `Send` and `Sync`.
+pub trait Position: Clone + Send + Sync {
+    type Move: Copy + PartialEq + fmt::Debug + Send + Sync;
+
+    fn to_move(&self) -> Player;
+
+    /// Empty once the game is over.
+    fn legal_moves(&self) -> Vec<Self::Move>;
+
+    /// Plays a move of `legal_moves` for the player to move.
+    fn play(&mut self, mv: Self::Move);
+
+    fn state(&self) -> BoardState;
+
+    /// A guess of how good an unfinished position is for `player`, used when a search stops early.
+    fn heuristic(&self, _player: Player) -> i32 {
+        0
+    }
+
+    /// The scores of all legal moves that a search `depth` moves deep would find,
+    /// if they are known without searching, like from a tablebase.
+    fn solved(&self, _depth: usize) -> Option<Vec<(Self::Move, i32)>> {
+        None
+    }
+
+    /// A key that positions with the same value share, so that a search can remember them.
+    /// `None` if the search has to look at every position again.
+    fn key(&self) -> Option<u64> {
+        None
+    }
+}
+
+/// Mixed into the key of a position when Player TWO is to move.
+const TWO_TO_MOVE: u64 = 0x5A17_C0DE_D00D_F00D;
+
+/// The classic game: a board and whose turn it is.
+#[derive(Debug, Default, Clone)]
+pub struct Turn {
+    pub board: Board,
+    pub player: Player,
+}
+
+impl Turn {
+    /// An empty board of `shape` with Player ONE to move.
+    pub fn new(shape: Shape, rules: Rules) -> Self {
+        Turn {
+            board: Board::new(shape).with_rules(rules),
+            player: Player::ONE,
+        }
+    }
+}
+
+impl Position for Turn {
+    type Move = (usize, usize);
+
+    fn to_move(&self) -> Player {
+        self.player
+    }
+
+    fn legal_moves(&self) -> Vec<Self::Move> {
+        match self.state() {
+            BoardState::ONGOING => self.board.playable_cells(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn play(&mut self, mv: Self::Move) {
+        self.board
+            .set_value(self.player, mv)
+            .expect("Engines only play legal moves");
+        self.player = self.player.other();
+    }
+
+    fn state(&self) -> BoardState {
+        self.board.get_board_state()
+    }
+
+    /// In misère, full lines are what a player has to stay away from.
+    fn heuristic(&self, player: Player) -> i32 {
+        let lines = lines_heuristic(&self.board, player);
+        if self.board.get_rules().misere {
+            -lines
+        } else {
+            lines
+        }
+    }
+
+    /// Rotated and mirrored boards are worth the same, as long as the same player moves.
+    fn key(&self) -> Option<u64> {
+        let side = TWO_TO_MOVE * self.player.index() as u64;
+        Some(self.board.canonical_key() ^ side)
+    }
+
+    /// A search that sees every game to its end finds what the tablebase knows.
+    fn solved(&self, depth: usize) -> Option<Vec<(Self::Move, i32)>> {
+        if depth < self.board.empty_count() {
+            return None;
+        }
+        let values = tablebase::move_values(&self.board, self.player)?;
+        Some(
+            values
+                .into_iter()
+                .map(|(field, value)| (field, value.score()))
+                .collect(),
+        )
+    }
+}
+
+/// The wild game: the player to move sets either mark, and a line of either mark wins.
+#[derive(Debug, Default, Clone)]
+pub struct Wild {
+    pub board: Board,
+    pub player: Player,
+}
+
+impl Wild {
+    /// An empty board of `shape` with Player ONE to move.
+    pub fn new(shape: Shape, rules: Rules) -> Self {
+        Wild {
+            board: Board::new(shape).with_rules(rules),
+            player: Player::ONE,
+        }
+    }
+}
+
+impl Position for Wild {
+    /// The cell and the mark set on it.
+    type Move = ((usize, usize), Player);
+
+    fn to_move(&self) -> Player {
+        self.player
+    }
+
+    fn legal_moves(&self) -> Vec<Self::Move> {
+        match self.state() {
+            BoardState::ONGOING => self
+                .board
+                .playable_cells()
+                .into_iter()
+                .flat_map(|field| [(field, Player::ONE), (field, Player::TWO)])
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn play(&mut self, (field, mark): Self::Move) {
+        self.board
+            .set_mark(self.player, mark, field)
+            .expect("Engines only play legal moves");
+        self.player = self.player.other();
+    }
+
+    fn state(&self) -> BoardState {
+        self.board.get_board_state()
+    }
+
+    fn key(&self) -> Option<u64> {
+        let side = TWO_TO_MOVE * self.player.index() as u64;
+        Some(self.board.canonical_key() ^ side)
+    }
+}
+
+/// Lines that only one player occupies are worth more the fuller they are.
+pub fn lines_heuristic(board: &Board, player: Player) -> i32 {
+    // Capped, so that long lines on big boards never add up to the score of a win
+    let score: i32 = board
+        .get_lines()
+        .iter()
+        .map(|line| {
+            let own = line.iter().filter(|&&cell| cell == Some(player)).count() as u32;
+            let other = line.iter().filter(|cell| cell.is_some()).count() as u32 - own;
+            let (own, other) = (own.min(4), other.min(4));
+            match (own, other) {
+                (0, 0) => 0,
+                (own, 0) => 10i32.pow(own),
+                (0, other) => -(10i32.pow(other)),
+                _ => 0,
+            }
+        })
+        .sum();
+    score.clamp(-WIN / 10, WIN / 10)
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Strategy {
+    RANDOM,
+    /// The move that looks best one move ahead.
+    GREEDY,
+    /// Alpha-beta search that looks the given number of moves ahead.
+    MINIMAX(usize),
+    /// Monte Carlo tree search with the given number of random playouts.
+    MCTS(usize),
+}
+
+impl FromStr for Strategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let number = |value: &str| {
+            value
+                .parse::<usize>()
+                .ok()
+                .filter(|&number| number > 0)
+                .ok_or_else(|| format!("'{}' is not a positive number", value))
+        };
+        match s.split_once(':') {
+            None if s == "random" => Ok(Self::RANDOM),
+            None if s == "greedy" => Ok(Self::GREEDY),
+            Some(("minimax", depth)) => Ok(Self::MINIMAX(number(depth)?)),
+            Some(("mcts", playouts)) => Ok(Self::MCTS(number(playouts)?)),
+            _ => Err(format!(
+                "Unknown strategy '{}'. Use random, greedy, minimax:<depth> or mcts:<playouts>",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Strategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RANDOM => write!(f, "random"),
+            Self::GREEDY => write!(f, "greedy"),
+            Self::MINIMAX(depth) => write!(f, "minimax:{}", depth),
+            Self::MCTS(playouts) => write!(f, "mcts:{}", playouts),
+        }
+    }
+}
+
+/// An AI player. Where several moves are equally good, it picks one at random.
+#[derive(Debug, Clone)]
+pub struct Engine {
+    strategy: Strategy,
+    rng: Rng,
+    threads: usize,
+}
+
+impl Engine {
+    pub fn new(strategy: Strategy) -> Self {
+        Engine {
+            strategy,
+            rng: Rng::from_entropy(),
+            threads: 1,
+        }
+    }
+
+    /// How many threads a minimax search may use. The moves it picks stay the same.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = Rng::seeded(seed);
+        self
+    }
+
+    /// `None` if there is no move left to play.
+    pub fn choose<P: Position>(&mut self, position: &P) -> Option<P::Move> {
+        let moves = position.legal_moves();
+        match self.strategy {
+            _ if moves.len() <= 1 => moves.first().copied(),
+            Strategy::RANDOM => self.rng.pick(&moves).copied(),
+            Strategy::GREEDY => {
+                let me = position.to_move();
+                let scored: Vec<(i32, P::Move)> = moves
+                    .iter()
+                    .map(|&mv| {
+                        let mut next = position.clone();
+                        next.play(mv);
+                        let score = match next.state().result() {
+                            Some(result) if result.winner == Some(me) => i32::MAX,
+                            Some(result) if result.winner.is_some() => i32::MIN,
+                            Some(_) => 0,
+                            None => next.heuristic(me),
+                        };
+                        (score, mv)
+                    })
+                    .collect();
+                let best = scored.iter().map(|(score, _)| *score).max()?;
+                let best_moves: Vec<P::Move> = scored
+                    .into_iter()
+                    .filter(|(score, _)| *score == best)
+                    .map(|(_, mv)| mv)
+                    .collect();
+                self.rng.pick(&best_moves).copied()
+            }
+            Strategy::MINIMAX(depth) => {
+                let (_, best_moves) = minimax::best_moves(position, depth, self.threads);
+                self.rng.pick(&best_moves).copied()
+            }
+            Strategy::MCTS(playouts) => mcts::search(position, playouts, &mut self.rng),
+        }
+    }
+}
+
+/// Plays one game between two engines from `start`. `engines[0]` plays Player ONE.
+/// Returns how the game ended and how many moves were played.
+pub fn play_out<P: Position>(start: &P, engines: &mut [Engine; 2]) -> (BoardState, usize) {
+    let mut position = start.clone();
+    let mut moves = 0;
+    loop {
+        let state = position.state();
+        if state != BoardState::ONGOING {
+            return (state, moves);
+        }
+        let engine = match position.to_move() {
+            Player::ONE => &mut engines[0],
+            Player::TWO => &mut engines[1],
+        };
+        match engine.choose(&position) {
+            Some(mv) => position.play(mv),
+            None => return (state, moves),
+        }
+        moves += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    /// ONE to move and able to win in the top row, TWO threatens the middle row.
+    fn one_wins_now() -> Turn {
+        let mut board = Board::default();
+        let _ = board.set_value(Player::ONE, (0, 0));
+        let _ = board.set_value(Player::ONE, (0, 1));
+        let _ = board.set_value(Player::TWO, (1, 0));
+        let _ = board.set_value(Player::TWO, (1, 1));
+        Turn {
+            board,
+            player: Player::ONE,
+        }
+    }
+
+    #[test]
+    fn can_parse_and_print_strategies() {
+        for name in ["random", "greedy", "minimax:4", "mcts:500"] {
+            let strategy: Strategy = name.parse().unwrap();
+            assert_eq!(name, strategy.to_string());
+        }
+        assert!("minimax:0".parse::<Strategy>().is_err());
+        assert!("alphazero".parse::<Strategy>().is_err());
+    }
+
+    #[test]
+    fn turn_plays_moves_alternately() {
+        let mut turn = Turn::default();
+        assert_eq!(9, turn.legal_moves().len());
+        turn.play((1, 1));
+        assert_eq!(Player::TWO, turn.to_move());
+        assert_eq!(Some(Player::ONE), turn.board[1][1]);
+        assert_eq!(8, turn.legal_moves().len());
+    }
+
+    #[test]
+    fn finished_game_has_no_legal_moves() {
+        let mut turn = one_wins_now();
+        turn.play((0, 2));
+        assert_eq!(BoardState::WON(Player::ONE), turn.state());
+        assert!(turn.legal_moves().is_empty());
+    }
+
+    #[test]
+    fn heuristic_prefers_open_lines() {
+        let mut turn = Turn::default();
+        turn.play((1, 1));
+        assert!(turn.heuristic(Player::ONE) > 0);
+        assert!(turn.heuristic(Player::TWO) < 0);
+    }
+
+    #[test]
+    fn long_lines_dont_look_like_a_win() {
+        let mut turn = Turn::new("8x8x8".parse().unwrap(), Rules::default());
+        for col in 0..7 {
+            let _ = turn.board.set_value(Player::ONE, (0, col));
+            let _ = turn.board.set_value(Player::TWO, (col + 1, col));
+        }
+        let mut engine = Engine::new(Strategy::MINIMAX(1));
+        assert_eq!(Some((0, 7)), engine.choose(&turn));
+    }
+
+    #[test]
+    fn every_strategy_but_random_takes_the_win() {
+        for strategy in [Strategy::GREEDY, Strategy::MINIMAX(2), Strategy::MCTS(300)] {
+            let mut engine = Engine::new(strategy).with_seed(3);
+            assert_eq!(Some((0, 2)), engine.choose(&one_wins_now()), "{}", strategy);
+        }
+    }
+
+    #[test]
+    fn minimax_blocks_the_opponent() {
+        let mut board = Board::default();
+        let _ = board.set_value(Player::ONE, (0, 0));
+        let _ = board.set_value(Player::ONE, (0, 1));
+        let _ = board.set_value(Player::TWO, (1, 0));
+        let _ = board.set_value(Player::ONE, (2, 2));
+        let turn = Turn {
+            board,
+            player: Player::TWO,
+        };
+        // TWO has to block the top row
+        let mut engine = Engine::new(Strategy::MINIMAX(3)).with_seed(1);
+        assert_eq!(Some((0, 2)), engine.choose(&turn));
+    }
+
+    #[test]
+    fn perfect_play_is_a_draw() {
+        let mut engines = [
+            Engine::new(Strategy::MINIMAX(9)).with_seed(1),
+            Engine::new(Strategy::MINIMAX(9)).with_seed(2),
+        ];
+        assert_eq!(
+            (BoardState::DRAW, 9),
+            play_out(&Turn::default(), &mut engines)
+        );
+    }
+
+    #[test]
+    fn random_engines_finish_their_games() {
+        let mut engines = [
+            Engine::new(Strategy::RANDOM).with_seed(5),
+            Engine::new(Strategy::RANDOM).with_seed(6),
+        ];
+        let (state, moves) = play_out(&Turn::default(), &mut engines);
+        assert_ne!(BoardState::ONGOING, state);
+        assert!((5..=9).contains(&moves));
+    }
+
+    #[test]
+    fn connect_four_engines_drop_their_marks() {
+        let rules = Rules {
+            gravity: true,
+            ..Rules::default()
+        };
+        let mut turn = Turn::new("6x7x4".parse().unwrap(), rules);
+        assert_eq!(7, turn.legal_moves().len());
+        assert!(turn.legal_moves().iter().all(|&(row, _)| row == 5));
+        // ONE has three on the bottom row, TWO has to drop into column 4
+        for field in [(5, 0), (4, 0), (5, 1), (4, 1), (5, 2)] {
+            turn.play(field);
+        }
+        let mut engine = Engine::new(Strategy::MINIMAX(2)).with_seed(1);
+        assert_eq!(Some((5, 3)), engine.choose(&turn));
+    }
+
+    #[test]
+    fn misere_engines_stay_away_from_lines() {
+        let rules = Rules {
+            misere: true,
+            ..Rules::default()
+        };
+        let mut turn = Turn::new(Shape::default(), rules);
+        // ONE holds a1 and b1, taking c1 would lose at once
+        for field in [(0, 0), (2, 0), (0, 1), (2, 2)] {
+            turn.play(field);
+        }
+        let mut engine = Engine::new(Strategy::MINIMAX(5)).with_seed(1);
+        assert_ne!(Some((0, 2)), engine.choose(&turn));
+        let mut greedy = Engine::new(Strategy::GREEDY).with_seed(1);
+        assert_ne!(Some((0, 2)), greedy.choose(&turn));
+        // Perfect misère play from the empty board is a draw as well
+        let (value, _) = minimax::best_moves(&Turn::new(Shape::default(), rules), 9, 1);
+        assert_eq!(0, value);
+    }
+
+    #[test]
+    fn wild_engines_pick_the_mark_that_wins() {
+        let mut wild = Wild::new(Shape::default(), Rules::default());
+        assert_eq!(18, wild.legal_moves().len());
+        // Two of TWO's marks in the middle row, ONE completes it with TWO's mark
+        wild.play(((1, 0), Player::TWO));
+        wild.play(((0, 0), Player::ONE));
+        wild.play(((1, 1), Player::TWO));
+        wild.play(((2, 2), Player::ONE));
+        let mut engine = Engine::new(Strategy::MINIMAX(1)).with_seed(1);
+        assert_eq!(Some(((1, 2), Player::TWO)), engine.choose(&wild));
+        wild.play(((1, 2), Player::TWO));
+        assert_eq!(BoardState::WON(Player::ONE), wild.state());
+    }
+
+    #[test]
+    fn first_player_wins_wild_tic_tac_toe() {
+        let (value, _) = minimax::best_moves(&Wild::new(Shape::default(), Rules::default()), 9, 1);
+        assert!(value > 0);
+    }
+}
@@ -0,0 +1,254 @@
+use crate::game::analysis::{self, Value};
+use crate::game::board::{Board, BoardState};
+use crate::game::player::Player;
+
+use std::fmt;
+
+/// A row, column or diagonal of the board.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Line {
+    ROW(usize),
+    COL(usize),
+    /// From the top left to the bottom right.
+    DIAG,
+    /// From the bottom left to the top right.
+    ANTIDIAG,
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ROW(row) => write!(f, "row {}", row + 1),
+            Self::COL(col) => write!(f, "column {}", col + 1),
+            Self::DIAG => write!(f, "diagonal from the top left"),
+            Self::ANTIDIAG => write!(f, "diagonal from the bottom left"),
+        }
+    }
+}
+
+impl Line {
+    /// Every line that goes through `field`.
+    fn through(board: &Board, field: (usize, usize)) -> Vec<Line> {
+        let (row, col) = field;
+        let mut lines = vec![Line::ROW(row), Line::COL(col)];
+        if row == col {
+            lines.push(Line::DIAG);
+        }
+        if row + col == board.get_height() - 1 {
+            lines.push(Line::ANTIDIAG);
+        }
+        lines
+    }
+
+    fn cells(self, board: &Board) -> Vec<(usize, usize)> {
+        let size = board.get_height();
+        match self {
+            Self::ROW(row) => (0..size).map(|col| (row, col)).collect(),
+            Self::COL(col) => (0..size).map(|row| (row, col)).collect(),
+            Self::DIAG => (0..size).map(|idx| (idx, idx)).collect(),
+            Self::ANTIDIAG => (0..size).map(|idx| (size - 1 - idx, idx)).collect(),
+        }
+    }
+}
+
+/// Why a move is suggested.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Motive {
+    WINS,
+    /// Stops the opponent from completing the line.
+    BLOCKS(Line),
+    ONLY,
+    /// Threatens to win in two places at once.
+    FORK,
+    /// Wins by force in the given number of own moves.
+    FORCES(usize),
+    /// Nobody can win against perfect play anymore.
+    HOLDS,
+    /// Every move loses against perfect play, this one takes the longest.
+    DELAYS,
+}
+
+impl fmt::Display for Motive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WINS => write!(f, "wins immediately"),
+            Self::BLOCKS(line) => write!(f, "blocks opponent's {}", line),
+            Self::ONLY => write!(f, "only move that avoids losing"),
+            Self::FORK => write!(f, "creates a fork"),
+            Self::FORCES(moves) => write!(f, "wins by force in {} moves", moves),
+            Self::HOLDS => write!(f, "keeps the draw"),
+            Self::DELAYS => write!(f, "the game is lost, this holds out the longest"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Hint {
+    pub field: (usize, usize),
+    pub motive: Motive,
+}
+
+impl fmt::Display for Hint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (row, col) = self.field;
+        write!(f, "row {}, column {}: {}", row + 1, col + 1, self.motive)
+    }
+}
+
+/// The best move for `player` and why. `None` if the game is already over.
+pub fn suggest(board: &Board, player: Player) -> Option<Hint> {
+    let evaluation = analysis::evaluate(board, player);
+    let candidates = evaluation.best_moves();
+    if candidates.is_empty() {
+        return None;
+    }
+    let hint = |field, motive| Some(Hint { field, motive });
+
+    if let Some(&field) = candidates.iter().find(|&&field| wins(board, player, field)) {
+        return hint(field, Motive::WINS);
+    }
+    let opponent = player.other();
+    for &field in &candidates {
+        if wins(board, opponent, field) {
+            let line = completed_line(board, opponent, field);
+            return hint(field, Motive::BLOCKS(line));
+        }
+    }
+    let not_losing = evaluation
+        .moves
+        .iter()
+        .filter(|(_, value)| *value >= Value::DRAW)
+        .count();
+    if evaluation.value >= Value::DRAW && not_losing == 1 && evaluation.moves.len() > 1 {
+        return hint(candidates[0], Motive::ONLY);
+    }
+    if let Some(&field) = candidates.iter().find(|&&field| {
+        let mut next = board.clone();
+        let _ = next.set_value(player, field);
+        next.empty_cells()
+            .filter(|&threat| wins(&next, player, threat))
+            .count()
+            >= 2
+    }) {
+        return hint(field, Motive::FORK);
+    }
+    let motive = match evaluation.value {
+        Value::DRAW => Motive::HOLDS,
+        Value::WIN(moves) => Motive::FORCES(moves.div_ceil(2)),
+        Value::LOSS(_) => Motive::DELAYS,
+    };
+    hint(candidates[0], motive)
+}
+
+fn wins(board: &Board, player: Player, field: (usize, usize)) -> bool {
+    let mut next = board.clone();
+    next.set_value(player, field).is_ok() && next.get_board_state() == BoardState::WON(player)
+}
+
+/// The line that `player` completes by taking `field`.
+fn completed_line(board: &Board, player: Player, field: (usize, usize)) -> Line {
+    let mut next = board.clone();
+    let _ = next.set_value(player, field);
+    Line::through(board, field)
+        .into_iter()
+        .find(|line| {
+            line.cells(&next)
+                .iter()
+                .all(|&(row, col)| next[row][col] == Some(player))
+        })
+        .expect("A winning move completes a line")
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn board(marks: &[(Player, (usize, usize))]) -> Board {
+        let mut board = Board::default();
+        for &(player, field) in marks {
+            let _ = board.set_value(player, field);
+        }
+        board
+    }
+
+    #[test]
+    fn suggests_the_winning_move() {
+        let board = board(&[
+            (Player::ONE, (0, 0)),
+            (Player::TWO, (1, 0)),
+            (Player::ONE, (0, 1)),
+            (Player::TWO, (1, 1)),
+        ]);
+        let hint = suggest(&board, Player::ONE).unwrap();
+        assert_eq!((0, 2), hint.field);
+        assert_eq!(Motive::WINS, hint.motive);
+    }
+
+    #[test]
+    fn suggests_blocking_the_opponent() {
+        let board = board(&[
+            (Player::ONE, (1, 0)),
+            (Player::TWO, (0, 0)),
+            (Player::ONE, (1, 1)),
+        ]);
+        let hint = suggest(&board, Player::TWO).unwrap();
+        assert_eq!("row 2, column 3: blocks opponent's row 2", hint.to_string());
+    }
+
+    #[test]
+    fn suggests_the_only_move_that_avoids_losing() {
+        let board = board(&[(Player::ONE, (0, 0))]);
+        let hint = suggest(&board, Player::TWO).unwrap();
+        assert_eq!((1, 1), hint.field);
+        assert_eq!(Motive::ONLY, hint.motive);
+    }
+
+    #[test]
+    fn suggests_a_fork() {
+        //  one | two |
+        //  ----+-----+----
+        //      | one |
+        //  ----+-----+----
+        //      |     | two
+        let board = board(&[
+            (Player::ONE, (0, 0)),
+            (Player::TWO, (2, 2)),
+            (Player::ONE, (1, 1)),
+            (Player::TWO, (0, 1)),
+        ]);
+        let hint = suggest(&board, Player::ONE).unwrap();
+        assert!([(1, 0), (2, 0)].contains(&hint.field));
+        assert_eq!(Motive::FORK, hint.motive);
+    }
+
+    #[test]
+    fn blocking_goes_before_forking() {
+        // ONE has to block at 0,2, which also forks row 1 and column 3
+        let board = board(&[
+            (Player::ONE, (0, 0)),
+            (Player::TWO, (1, 1)),
+            (Player::ONE, (2, 2)),
+            (Player::TWO, (2, 0)),
+        ]);
+        let hint = suggest(&board, Player::ONE).unwrap();
+        assert_eq!((0, 2), hint.field);
+        assert_eq!(Motive::BLOCKS(Line::ANTIDIAG), hint.motive);
+    }
+
+    #[test]
+    fn empty_board_holds_the_draw() {
+        let hint = suggest(&Board::default(), Player::ONE).unwrap();
+        assert_eq!(Motive::HOLDS, hint.motive);
+    }
+
+    #[test]
+    fn finished_game_has_no_hint() {
+        let board = board(&[
+            (Player::ONE, (0, 0)),
+            (Player::ONE, (0, 1)),
+            (Player::ONE, (0, 2)),
+        ]);
+        assert_eq!(None, suggest(&board, Player::TWO));
+    }
+}
@@ -1,25 +1,81 @@
+pub mod analysis;
+pub mod bench;
 mod board;
+pub mod clock;
+pub mod engine;
+pub mod hint;
+pub mod notation;
 pub mod player;
+pub mod rating;
+pub mod series;
+pub mod stats;
+pub mod tablebase;
+pub mod tournament;
+pub mod variant;
 
-use board::{Board, BoardState};
+pub use board::{Rules, Shape};
+
+use board::{Board, BoardState, GameResult, Reason};
+use clock::{Clock, Remaining, TimeControl};
+use notation::GameRecord;
 use player::Player;
-use std::io::stdin;
+use rating::{Ratings, System};
+use stats::{Record, Stats, Store};
+use std::fs;
+use std::io::{self, stdin};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
 
-const fn options() -> [&'static str; 4] {
+const fn options() -> [&'static str; 12] {
     [
         "These are your options:",
         "",
-        "q | quit    exits TicTacToe",
+        "q | quit      exits TicTacToe",
+        "resign        gives up the game",
+        "offer draw    offers your opponent a draw",
+        "accept        accepts a draw offer",
+        "decline       declines a draw offer",
+        "hint          suggests a move and tells you why",
+        "stats         shows the statistics of both players",
+        "leaderboard   shows everybody's statistics",
+        "ratings       shows everybody's rating",
         "",
     ]
 }
 
+#[derive(Debug, PartialEq)]
+enum Command {
+    QUIT,
+    RESIGN,
+    OFFER,
+    ACCEPT,
+    DECLINE,
+    STATS,
+    LEADERBOARD,
+    RATINGS,
+    HINT,
+}
+
 #[derive(Default)]
 pub struct Game {
     pub board: Board,
     player: Player,
+    /// The layer chosen for the move, only asked for on a cube.
+    layer: Option<usize>,
+    /// The row of the grid, where the layers of a cube are stacked.
     row: Option<usize>,
     col: Option<usize>,
+    /// The mark chosen for the cell, only asked for in wild games.
+    mark: Option<Player>,
+    clock: Option<Clock>,
+    draw_offer: Option<Player>,
+    result: Option<BoardState>,
+    /// The names of Player ONE and Player TWO. Only named players get statistics.
+    names: Option<[String; 2]>,
+    rating_system: System,
+    history: Vec<(Player, (usize, usize))>,
 }
 
 impl Game {
@@ -27,102 +83,577 @@ impl Game {
         Game::default()
     }
 
-    pub fn play(&mut self) {
+    pub fn with_first_player(mut self, player: Player) -> Self {
+        self.player = player;
+        self
+    }
+
+    pub fn with_time_control(mut self, control: TimeControl) -> Self {
+        self.clock = Some(Clock::new(control));
+        self
+    }
+
+    pub fn with_names(mut self, names: [String; 2]) -> Self {
+        self.names = Some(names);
+        self
+    }
+
+    pub fn with_rating_system(mut self, system: System) -> Self {
+        self.rating_system = system;
+        self
+    }
+
+    pub fn with_shape(mut self, shape: Shape) -> Self {
+        self.board = Board::new(shape).with_rules(self.board.get_rules());
+        self
+    }
+
+    pub fn with_rules(mut self, rules: Rules) -> Self {
+        self.board = Board::new(self.board.get_shape()).with_rules(rules);
+        self
+    }
+
+    /// Hints and analysis solve the game, that only works on the classic board.
+    fn is_solvable(&self) -> bool {
+        self.board.get_shape() == Shape::default() && self.board.get_rules() == Rules::default()
+    }
+
+    fn ruleset(&self) -> String {
+        stats::ruleset(self.board.get_shape(), self.board.get_rules())
+    }
+
+    /// With gravity a column is all it takes, the mark falls down the column.
+    fn has_gravity(&self) -> bool {
+        self.board.get_rules().gravity
+    }
+
+    /// On a cube the player first picks the layer.
+    fn needs_layer(&self) -> bool {
+        self.board.get_shape().layers > 1 && self.layer.is_none()
+    }
+
+    /// In wild games the player still has to pick a mark for the chosen cell.
+    fn needs_mark(&self) -> bool {
+        self.board.get_rules().wild && self.mark.is_none()
+    }
+
+    fn get_state(&mut self) -> BoardState {
+        match self.result.take() {
+            Some(result) => result,
+            None => self.board.get_board_state(),
+        }
+    }
+
+    pub fn play(&mut self) -> GameResult {
+        let result = self.play_game();
+        if let Some(record) = self.annotated_record(result) {
+            offer_analysis(&[(1, record)]);
+        }
+        print_lines(["Thanks for playing. Come back soon!"]);
+        result
+    }
+
+    /// Plays until the game has a result and saves it. A series offers the analysis of its
+    /// games once all of them are played.
+    fn play_game(&mut self) -> GameResult {
         print_lines(["Welcome to TicTacToe."]);
 
         print_lines(options());
 
-        loop {
-            match self.board.get_board_state() {
+        let result = loop {
+            let state = self.get_state();
+            match state {
+                BoardState::WON(winner) if self.board.get_rules().misere => {
+                    print_lines([format!(
+                        "Player {:?} completed a line. Congratulations! Player {:?} won!",
+                        winner.other(),
+                        winner
+                    )]);
+                    self.board.draw();
+                    println!();
+                }
                 BoardState::WON(winner) => {
                     print_lines([format!("Congratulations! Player {:?} won!", winner)]);
                     self.board.draw();
                     println!();
-                    break;
                 }
                 BoardState::DRAW => {
                     print_lines(["Game Over. Nobody won!"]);
-                    break;
+                }
+                BoardState::ENDED(winner, reason) => {
+                    print_lines([ended_message(winner, reason)]);
+                    self.board.draw();
+                    println!();
                 }
                 BoardState::ONGOING => { /* just keep going */ }
             }
+            if let Some(result) = state.result() {
+                break result;
+            }
 
-            match (self.row, self.col) {
-                (None, None) => {
+            match (self.draw_offer, self.row, self.col) {
+                (Some(offered_by), _, _) => {
+                    print_lines([format!(
+                        "Player {:?} offers a draw. Player {:?}, do you accept? (accept | decline)",
+                        offered_by,
+                        offered_by.other()
+                    )]);
+                }
+                (None, None, None) if self.layer.is_some() => {
+                    print_lines([format!("Please enter a row number{}", self.time_left())]);
+                }
+                (None, None, None) => {
+                    if let Some(clock) = &mut self.clock {
+                        clock.start(self.player, Instant::now());
+                    }
                     print_lines([format!("It is player {:?}'s' turn", self.player)]);
                     self.board.draw();
-                    print_lines(["Please enter a row number"]);
+                    let asked = if self.needs_layer() {
+                        "layer"
+                    } else if self.has_gravity() {
+                        "column"
+                    } else {
+                        "row"
+                    };
+                    print_lines([format!(
+                        "Please enter a {} number{}",
+                        asked,
+                        self.time_left()
+                    )]);
                 }
-                (Some(_), None) => {
-                    print_lines(["Please enter a column number"]);
+                (None, Some(_), None) => {
+                    print_lines([format!("Please enter a column number{}", self.time_left())]);
                 }
-                (Some(row), Some(col)) => match self.board.set_value(self.player, (row, col)) {
-                    Ok(()) => {
-                        self.swap_player();
-                        self.reset_row_col();
-                        continue;
-                    }
-                    Err(e) => {
-                        print_lines([format!("{}", e)]);
-                        self.reset_row_col();
+                (None, Some(_), Some(_)) if self.is_flagged() => {
+                    self.result = Some(BoardState::ENDED(
+                        Some(self.player.other()),
+                        Reason::TIMEOUT,
+                    ));
+                    continue;
+                }
+                (None, Some(_), Some(_)) if self.needs_mark() => {
+                    print_lines([format!(
+                        "Which mark, x ({}) or o ({})?{}",
+                        Player::ONE,
+                        Player::TWO,
+                        self.time_left()
+                    )]);
+                }
+                (None, Some(row), Some(col)) => {
+                    let mark = self.mark.unwrap_or(self.player);
+                    match self.board.set_mark(self.player, mark, (row, col)) {
+                        Ok(()) => {
+                            if let Some(clock) = &mut self.clock {
+                                clock.punch(Instant::now());
+                            }
+                            self.history.push((self.player, (row, col)));
+                            self.swap_player();
+                            self.reset_row_col();
+                            continue;
+                        }
+                        Err(e) => {
+                            print_lines([e.to_string()]);
+                            self.reset_row_col();
+                        }
                     }
-                },
-                (None, Some(_)) => unreachable!("Col can not be set before Row"),
+                }
+                (None, None, Some(_)) => unreachable!("Col can not be set before Row"),
             }
 
-            let mut input = String::new();
-            let row_col_input = get_input(&mut input);
-            match row_col_input {
-                val if is_quit(val) => {
-                    break;
+            let timeout = self
+                .clock
+                .as_ref()
+                .map(|clock| clock.remaining(self.player, Instant::now()));
+            let input = match get_input(timeout) {
+                Some(input) => input,
+                None => {
+                    self.result = Some(BoardState::ENDED(
+                        Some(self.player.other()),
+                        Reason::TIMEOUT,
+                    ));
+                    continue;
+                }
+            };
+            match (get_command(&input), self.draw_offer) {
+                (Some(Command::QUIT), _) => {
+                    self.result = Some(BoardState::ENDED(None, Reason::ABANDONMENT));
+                }
+                (Some(Command::ACCEPT), Some(_)) => {
+                    self.result = Some(BoardState::ENDED(None, Reason::AGREEMENT));
+                }
+                (Some(Command::DECLINE), Some(offered_by)) => {
+                    self.draw_offer = None;
+                    print_lines([format!(
+                        "Player {:?} declined the draw. Player {:?} keeps playing.",
+                        offered_by.other(),
+                        offered_by
+                    )]);
+                }
+                (_, Some(_)) => {
+                    print_lines(["Please answer the draw offer with accept or decline"]);
                 }
-                val => match val.parse::<u32>() {
-                    Ok(value) if self.row.is_none() && value > 0 => {
-                        self.row = Some((value - 1) as usize);
-                        continue;
+                (Some(Command::RESIGN), None) => {
+                    self.result = Some(BoardState::ENDED(
+                        Some(self.player.other()),
+                        Reason::RESIGNATION,
+                    ));
+                }
+                (Some(Command::OFFER), None) => {
+                    self.draw_offer = Some(self.player);
+                }
+                (Some(Command::ACCEPT), None) | (Some(Command::DECLINE), None) => {
+                    print_lines(["There is no draw offer to answer."]);
+                }
+                (Some(Command::STATS), None) => self.show_stats(),
+                (Some(Command::LEADERBOARD), None) => show_leaderboard(&self.ruleset()),
+                (Some(Command::RATINGS), None) => show_ratings(self.rating_system, &self.ruleset()),
+                (Some(Command::HINT), None) if !self.is_solvable() => {
+                    print_lines(["Hints are only available on the classic 3x3 board."]);
+                }
+                (Some(Command::HINT), None) => {
+                    if let Some(hint) = hint::suggest(&self.board, self.player) {
+                        print_lines([format!("Hint: {}", hint)]);
+                    }
+                }
+                (None, None) if self.row.is_some() && self.col.is_some() => match input.as_str() {
+                    "x" => self.mark = Some(Player::ONE),
+                    "o" => self.mark = Some(Player::TWO),
+                    _ => print_lines(["Please enter x or o"]),
+                },
+                (None, None) => match input.parse::<u32>() {
+                    Ok(value) if self.has_gravity() && value > 0 => {
+                        let col = (value - 1) as usize;
+                        match self.board.drop_row(col) {
+                            Some(row) => {
+                                self.row = Some(row);
+                                self.col = Some(col);
+                            }
+                            None if col < self.board.get_width() => {
+                                print_lines([format!("Column {} is full.", value)]);
+                            }
+                            None => print_lines([format!(
+                                "Please enter a valid number. The range is: 1 - {}",
+                                self.input_range(),
+                            )]),
+                        }
+                    }
+                    Ok(value) if self.needs_layer() && self.in_range(value) => {
+                        self.layer = Some((value - 1) as usize);
+                    }
+                    Ok(value) if self.row.is_none() && self.in_range(value) => {
+                        let layer = self.layer.unwrap_or(0);
+                        let shape = self.board.get_shape();
+                        self.row = Some(shape.field(layer, (value - 1) as usize, 0).0);
                     }
-                    Ok(value) if value > 0 => {
+                    Ok(value) if self.row.is_some() && value > 0 => {
                         self.col = Some((value - 1) as usize);
-                        continue;
                     }
                     Ok(_) => {
                         print_lines([format!(
                             "Please enter a valid number. The range is: 1 - {}",
-                            self.board.get_height(),
+                            self.input_range(),
                         )]);
                     }
                     Err(_) => {
                         print_lines([format!(
                             "Please enter a digit between 1 and {}",
-                            self.board.get_height(),
+                            self.input_range(),
                         )]);
-                        continue;
                     }
                 },
             }
+        };
+
+        print_lines([format!("Result: {}", result)]);
+        self.save(result);
+        result
+    }
+
+    fn save(&self, result: GameResult) {
+        if let Some(names) = &self.names {
+            let record =
+                Record::new(names.clone(), result, self.history.len()).with_ruleset(self.ruleset());
+            let saved = Store::open().and_then(|store| {
+                store.record(&record)?;
+                store.records_of(&record.ruleset)
+            });
+            match saved {
+                Ok(records) if result.reason != Reason::ABANDONMENT => {
+                    let ratings = Ratings::from_records(self.rating_system, &records);
+                    print_lines(names.iter().map(|name| {
+                        let rating = ratings.rating(name);
+                        let before = ratings
+                            .history(name)
+                            .last()
+                            .map_or(rating, |change| change.before);
+                        format!(
+                            "{}: {:.0} -> {:.0} ({:+.0})",
+                            name,
+                            before.value,
+                            rating.value,
+                            rating.value - before.value
+                        )
+                    }));
+                }
+                Ok(_) => {}
+                Err(e) => print_lines([format!("Couldn't save the statistics. Err: {}", e)]),
+            }
         }
+    }
 
-        print_lines(["Thanks for playing. Come back soon!"]);
+    /// The game with the mistakes marked, if it was played out on the classic board.
+    fn annotated_record(&self, result: GameResult) -> Option<GameRecord> {
+        if ![Reason::LINE, Reason::FULL].contains(&result.reason) || !self.is_solvable() {
+            return None;
+        }
+        let first = self.history.first()?.0;
+        let fields: Vec<(usize, usize)> = self.history.iter().map(|&(_, field)| field).collect();
+        let mut record = GameRecord::new(first, &fields, result);
+        if let Some(names) = &self.names {
+            record = record.with_names(names.clone());
+        }
+        Some(record.with_annotations(analysis::annotate(first, &fields)))
+    }
+
+    fn show_stats(&self) {
+        let names = match &self.names {
+            Some(names) => names,
+            None => {
+                print_lines(["Name the players with --players to keep statistics."]);
+                return;
+            }
+        };
+        match stored_records(&self.ruleset()) {
+            Ok(records) => {
+                for name in names {
+                    print_lines(Stats::of(name, &records).lines());
+                }
+            }
+            Err(e) => print_lines([format!("Couldn't read the statistics. Err: {}", e)]),
+        }
+    }
+
+    fn is_flagged(&self) -> bool {
+        self.clock
+            .as_ref()
+            .is_some_and(|clock| clock.is_flagged(self.player, Instant::now()))
+    }
+
+    fn time_left(&self) -> String {
+        match &self.clock {
+            Some(clock) => format!(
+                " [{} left]",
+                Remaining(clock.remaining(self.player, Instant::now()))
+            ),
+            None => String::new(),
+        }
+    }
+
+    /// How many layers, rows or columns there are to choose from, whichever is asked for.
+    fn input_range(&self) -> usize {
+        match self.row {
+            None if self.needs_layer() => self.board.get_shape().layers,
+            None if !self.has_gravity() => self.board.get_height(),
+            None => self.board.get_width(),
+            Some(_) => self.board.get_width(),
+        }
+    }
+
+    /// A row of a cube must not run into the next layer, so layers and rows are checked here.
+    /// A column too far out is caught when the mark is set.
+    fn in_range(&self, value: u32) -> bool {
+        (1..=self.input_range()).contains(&(value as usize))
     }
 
     fn reset_row_col(&mut self) {
+        self.layer = None;
         self.row = None;
         self.col = None;
+        self.mark = None;
     }
 
     fn swap_player(&mut self) {
-        self.player = if self.player == Player::ONE {
-            Player::TWO
-        } else {
-            Player::ONE
+        self.player = self.player.other();
+    }
+}
+
+fn stored_records(ruleset: &str) -> io::Result<Vec<Record>> {
+    Store::open().and_then(|store| store.records_of(ruleset))
+}
+
+pub fn show_leaderboard(ruleset: &str) {
+    match stored_records(ruleset) {
+        Ok(records) => print_lines(stats::leaderboard_lines(&records)),
+        Err(e) => print_lines([format!("Couldn't read the statistics. Err: {}", e)]),
+    }
+}
+
+pub fn show_stats_of(name: &str, ruleset: &str) {
+    match stored_records(ruleset) {
+        Ok(records) => print_lines(Stats::of(name, &records).lines()),
+        Err(e) => print_lines([format!("Couldn't read the statistics. Err: {}", e)]),
+    }
+}
+
+pub fn show_ratings(system: System, ruleset: &str) {
+    match stored_records(ruleset) {
+        Ok(records) => print_lines(Ratings::from_records(system, &records).lines()),
+        Err(e) => print_lines([format!("Couldn't read the statistics. Err: {}", e)]),
+    }
+}
+
+pub fn show_rating_history(system: System, name: &str, ruleset: &str) {
+    match stored_records(ruleset) {
+        Ok(records) => print_lines(Ratings::from_records(system, &records).history_lines(name)),
+        Err(e) => print_lines([format!("Couldn't read the statistics. Err: {}", e)]),
+    }
+}
+
+/// Analyses a game record from a file, whether or not it was annotated before.
+pub fn show_analysis_of(path: &str) {
+    let record = fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| text.parse::<GameRecord>());
+    match record {
+        Ok(record) => {
+            let annotations = analysis::annotate(record.first, &record.fields());
+            print_lines(analysis::lines(&record.with_annotations(annotations)));
         }
+        Err(e) => print_lines([format!("Couldn't read the game record. Err: {}", e)]),
     }
 }
 
-fn get_input<'a>(mut input: &'a mut String) -> &'a str {
-    match stdin().read_line(&mut input) {
-        Ok(_) => input.trim(),
-        Err(e) => panic!("Ooops. Couldn't read from stdin. Err: {}", e),
+/// Shows how many positions the tablebase covers, or writes a fresh table to `output`.
+pub fn show_tablebase(output: Option<&str>) {
+    match output {
+        Some(path) => match fs::write(path, tablebase::generate()) {
+            Ok(()) => print_lines([format!("Wrote the tablebase to {}", path)]),
+            Err(e) => print_lines([format!("Couldn't write the tablebase. Err: {}", e)]),
+        },
+        None => print_lines(tablebase::counts().lines()),
+    }
+}
+
+/// Solves a position given in notation and shows the value of every move.
+pub fn show_evaluation_of(position: &str) {
+    match notation::parse_position(position) {
+        Ok((board, player)) => {
+            board.draw();
+            println!();
+            print_lines([format!("Player {:?} to move", player)]);
+            print_lines(analysis::evaluate(&board, player).lines());
+        }
+        Err(e) => print_lines([format!("Couldn't read the position. Err: {}", e)]),
+    }
+}
+
+/// Lets the players look for the mistakes of the games they just finished, given with their
+/// numbers. With more than one game the commands need the number of the game.
+fn offer_analysis(games: &[(usize, GameRecord)]) {
+    match games {
+        [] => return,
+        [_] => print_lines([
+            "analyse       goes through the game and shows the mistakes",
+            "save <file>   saves the annotated game as a game record",
+            "Press enter to finish.",
+        ]),
+        _ => print_lines([
+            "analyse <game>        goes through a game and shows the mistakes".to_string(),
+            "save <game> <file>    saves the annotated game as a game record".to_string(),
+            format!(
+                "Games that can be analysed: {}",
+                games
+                    .iter()
+                    .map(|(number, _)| number.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            "Press enter to finish.".to_string(),
+        ]),
+    }
+    loop {
+        let input = get_input(None).unwrap_or_default();
+        let (command, rest) = input.split_once(' ').unwrap_or((input.as_str(), ""));
+        // The number of the game can be left out when there is only one
+        let rest = rest.trim();
+        let (number, after) = rest.split_once(' ').unwrap_or((rest, ""));
+        let (record, rest) = match (number.parse::<usize>(), games) {
+            (Ok(number), _) => (
+                games
+                    .iter()
+                    .find(|(game, _)| *game == number)
+                    .map(|(_, record)| record),
+                after.trim(),
+            ),
+            (Err(_), [(_, record)]) => (Some(record), rest),
+            (Err(_), _) => (None, rest),
+        };
+        match (command, record, rest) {
+            ("analyse" | "analyze", Some(record), "") => print_lines(analysis::lines(record)),
+            ("save", Some(record), path) if !path.is_empty() => {
+                match fs::write(path, record.to_string()) {
+                    Ok(()) => print_lines([format!("Saved the game to {}", path)]),
+                    Err(e) => print_lines([format!("Couldn't save the game. Err: {}", e)]),
+                }
+            }
+            ("analyse" | "analyze" | "save", None, _) => {
+                print_lines(["Please enter the number of a game that can be analysed"]);
+            }
+            _ => break,
+        }
+    }
+}
+
+fn ended_message(winner: Option<Player>, reason: Reason) -> String {
+    match (winner, reason) {
+        (Some(winner), Reason::TIMEOUT) => format!("Time is up! Player {:?} won on time!", winner),
+        (Some(winner), Reason::RESIGNATION) => {
+            format!("Player {:?} won. The opponent resigned!", winner)
+        }
+        (None, Reason::AGREEMENT) => "Game Over. The players agreed to a draw!".to_string(),
+        (None, Reason::ABANDONMENT) => "The game was abandoned.".to_string(),
+        (winner, reason) => format!("Game Over ({}). Winner: {:?}", reason, winner),
+    }
+}
+
+/// Reads stdin on its own thread, so that waiting for input can time out.
+/// There is only one such thread, games played one after another share it.
+fn read_lines() -> &'static Mutex<Receiver<String>> {
+    static LINES: OnceLock<Mutex<Receiver<String>>> = OnceLock::new();
+    LINES.get_or_init(|| Mutex::new(spawn_reader()))
+}
+
+fn spawn_reader() -> Receiver<String> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || loop {
+        let mut input = String::new();
+        match stdin().read_line(&mut input) {
+            Ok(0) => break,
+            Ok(_) => {
+                if sender.send(input.trim().to_string()).is_err() {
+                    break;
+                }
+            }
+            Err(e) => panic!("Ooops. Couldn't read from stdin. Err: {}", e),
+        }
+    });
+    receiver
+}
+
+/// Waits for the next line of input. Returns `None` if the `timeout` elapsed first.
+fn get_input(timeout: Option<Duration>) -> Option<String> {
+    let lines = read_lines()
+        .lock()
+        .expect("Only one game reads input at a time");
+    let input = match timeout {
+        Some(timeout) => lines.recv_timeout(timeout),
+        None => lines.recv().map_err(|_| RecvTimeoutError::Disconnected),
+    };
+    match input {
+        Ok(input) => Some(input),
+        Err(RecvTimeoutError::Timeout) => None,
+        // stdin was closed, there is nobody left to play
+        Err(RecvTimeoutError::Disconnected) => Some("quit".to_string()),
     }
 }
 
@@ -130,6 +661,21 @@ fn is_quit(command: &str) -> bool {
     ["q", "quit"].contains(&command)
 }
 
+fn get_command(input: &str) -> Option<Command> {
+    match input {
+        val if is_quit(val) => Some(Command::QUIT),
+        "resign" => Some(Command::RESIGN),
+        "offer draw" | "offer" => Some(Command::OFFER),
+        "accept" => Some(Command::ACCEPT),
+        "decline" => Some(Command::DECLINE),
+        "stats" => Some(Command::STATS),
+        "leaderboard" => Some(Command::LEADERBOARD),
+        "ratings" => Some(Command::RATINGS),
+        "hint" => Some(Command::HINT),
+        _ => None,
+    }
+}
+
 fn print_lines<I>(lines: I)
 where
     I: IntoIterator,
@@ -144,8 +690,9 @@ where
 
 #[cfg(test)]
 mod tests {
-    use crate::game::is_quit;
+    use crate::game::board::Board;
     use crate::game::player::Player;
+    use crate::game::{get_command, is_quit, Command};
     use crate::Game;
 
     #[test]
@@ -153,9 +700,26 @@ mod tests {
         let mut game = Game::default();
         game.row = Some(1);
         game.col = Some(3);
+        game.mark = Some(Player::TWO);
+        game.layer = Some(2);
         game.reset_row_col();
+        assert_eq!(None, game.layer);
         assert_eq!(None, game.row);
         assert_eq!(None, game.col);
+        assert_eq!(None, game.mark);
+    }
+
+    #[test]
+    fn cubes_ask_for_the_layer_first() {
+        let mut game = Game::default();
+        game.board = Board::new("2x3x3x3".parse().unwrap());
+        assert!(game.needs_layer());
+        assert_eq!(2, game.input_range());
+        assert!(!game.in_range(3));
+        game.layer = Some(1);
+        assert_eq!(3, game.input_range());
+        assert!(game.in_range(3));
+        assert!(!game.in_range(4));
     }
 
     #[test]
@@ -174,4 +738,18 @@ mod tests {
         assert!(is_quit("quit"));
         assert!(!is_quit("not q"));
     }
+
+    #[test]
+    fn commands_get_recognized() {
+        assert_eq!(Some(Command::QUIT), get_command("q"));
+        assert_eq!(Some(Command::RESIGN), get_command("resign"));
+        assert_eq!(Some(Command::OFFER), get_command("offer draw"));
+        assert_eq!(Some(Command::ACCEPT), get_command("accept"));
+        assert_eq!(Some(Command::DECLINE), get_command("decline"));
+        assert_eq!(Some(Command::STATS), get_command("stats"));
+        assert_eq!(Some(Command::LEADERBOARD), get_command("leaderboard"));
+        assert_eq!(Some(Command::RATINGS), get_command("ratings"));
+        assert_eq!(Some(Command::HINT), get_command("hint"));
+        assert_eq!(None, get_command("2"));
+    }
 }
@@ -0,0 +1,360 @@
+use crate::game::analysis::{Annotation, Mark};
+use crate::game::board::{Board, GameResult, Reason};
+use crate::game::player::Player;
+
+use std::fmt;
+use std::str::FromStr;
+
+/// The name of a cell: the column as a letter, then the row as a number. `(0, 0)` is `a1`.
+pub fn cell_name(field: (usize, usize)) -> String {
+    let (row, col) = field;
+    format!("{}{}", (b'a' + col as u8) as char, row + 1)
+}
+
+pub fn parse_cell(name: &str) -> Result<(usize, usize), String> {
+    let mut chars = name.chars();
+    let col = match chars.next() {
+        Some(letter @ 'a'..='z') => letter as usize - 'a' as usize,
+        _ => return Err(format!("'{}' is not a cell like b2", name)),
+    };
+    match chars.as_str().parse::<usize>() {
+        Ok(row) if row > 0 => Ok((row - 1, col)),
+        _ => Err(format!("'{}' is not a cell like b2", name)),
+    }
+}
+
+/// Reads a position: the rows from top to bottom separated by `/`, with `x` for Player ONE,
+/// `o` for Player TWO and `.` for an empty cell, like `x.o/.x./...`. The player to move can
+/// follow after a space. Without it, the player with fewer marks moves, Player ONE on a tie.
+pub fn parse_position(s: &str) -> Result<(Board, Player), String> {
+    let mut board = Board::default();
+    let (rows, to_move) = match s.trim().split_once(' ') {
+        Some((rows, to_move)) => (rows, Some(to_move.trim())),
+        None => (s.trim(), None),
+    };
+    let rows: Vec<&str> = rows.split('/').collect();
+    if rows.len() != board.get_height() {
+        return Err(format!(
+            "'{}' needs {} rows separated by /",
+            s,
+            board.get_height()
+        ));
+    }
+    let mut marks = [0, 0];
+    for (row, cells) in rows.iter().enumerate() {
+        if cells.chars().count() != board.get_width() {
+            return Err(format!(
+                "Row {} of '{}' needs {} cells",
+                row + 1,
+                s,
+                board.get_width()
+            ));
+        }
+        for (col, cell) in cells.chars().enumerate() {
+            let player = match cell {
+                'x' => Player::ONE,
+                'o' => Player::TWO,
+                '.' => continue,
+                _ => return Err(format!("'{}' is not a cell, use x, o or .", cell)),
+            };
+            marks[player.index()] += 1;
+            board.set_value(player, (row, col))?;
+        }
+    }
+    let player = match to_move {
+        Some("x") => Player::ONE,
+        Some("o") => Player::TWO,
+        Some(other) => return Err(format!("'{}' is not a player to move, use x or o", other)),
+        None if marks[1] < marks[0] => Player::TWO,
+        None => Player::ONE,
+    };
+    Ok((board, player))
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Move {
+    pub field: (usize, usize),
+    pub annotation: Option<Annotation>,
+}
+
+/// A finished game as text. A few tags come first, then the numbered moves.
+/// Annotated moves carry a mark and the better move:
+///
+/// ```text
+/// [One "alice"]
+/// [Two "bob"]
+/// [First "one"]
+/// [Result "1-0"]
+/// [Reason "line completed"]
+///
+/// 1. b2 2. b1?? {better a1} 3. a1 ...
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct GameRecord {
+    pub names: Option<[String; 2]>,
+    pub first: Player,
+    pub moves: Vec<Move>,
+    pub result: GameResult,
+}
+
+impl GameRecord {
+    pub fn new(first: Player, fields: &[(usize, usize)], result: GameResult) -> Self {
+        GameRecord {
+            names: None,
+            first,
+            moves: fields
+                .iter()
+                .map(|&field| Move {
+                    field,
+                    annotation: None,
+                })
+                .collect(),
+            result,
+        }
+    }
+
+    pub fn with_names(mut self, names: [String; 2]) -> Self {
+        self.names = Some(names);
+        self
+    }
+
+    pub fn with_annotations(mut self, annotations: Vec<Option<Annotation>>) -> Self {
+        for (mv, annotation) in self.moves.iter_mut().zip(annotations) {
+            mv.annotation = annotation;
+        }
+        self
+    }
+
+    pub fn fields(&self) -> Vec<(usize, usize)> {
+        self.moves.iter().map(|mv| mv.field).collect()
+    }
+}
+
+impl fmt::Display for GameRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some([one, two]) = &self.names {
+            writeln!(f, "[One \"{}\"]", one)?;
+            writeln!(f, "[Two \"{}\"]", two)?;
+        }
+        writeln!(f, "[First \"{}\"]", player_tag(self.first))?;
+        writeln!(f, "[Result \"{}\"]", score(self.result))?;
+        writeln!(f, "[Reason \"{}\"]", self.result.reason)?;
+        writeln!(f)?;
+        let moves: Vec<String> = self
+            .moves
+            .iter()
+            .enumerate()
+            .map(|(idx, mv)| {
+                let mut text = format!("{}. {}", idx + 1, cell_name(mv.field));
+                if let Some(annotation) = mv.annotation {
+                    text.push_str(&format!(
+                        "{} {{better {}}}",
+                        annotation.mark.symbol(),
+                        cell_name(annotation.better)
+                    ));
+                }
+                text
+            })
+            .collect();
+        writeln!(f, "{}", moves.join(" "))
+    }
+}
+
+impl FromStr for GameRecord {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (mut one, mut two, mut first, mut winner, mut reason) = (None, None, None, None, None);
+        let mut moves = Vec::new();
+        for line in s.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            if let Some(tag) = line.strip_prefix('[') {
+                let (key, value) = tag
+                    .strip_suffix("\"]")
+                    .and_then(|tag| tag.split_once(" \""))
+                    .ok_or_else(|| format!("'{}' is not a tag like [Key \"value\"]", line))?;
+                match key {
+                    "One" => one = Some(value.to_string()),
+                    "Two" => two = Some(value.to_string()),
+                    "First" => first = Some(parse_player(value)?),
+                    "Result" => winner = Some(parse_score(value)?),
+                    "Reason" => reason = Some(value.parse::<Reason>()?),
+                    _ => {}
+                }
+            } else {
+                parse_moves(line, &mut moves)?;
+            }
+        }
+        let names = match (one, two) {
+            (Some(one), Some(two)) => Some([one, two]),
+            (None, None) => None,
+            _ => return Err("A record names both players or neither".to_string()),
+        };
+        Ok(GameRecord {
+            names,
+            first: first.unwrap_or_default(),
+            moves,
+            result: GameResult {
+                winner: winner.ok_or("The record has no Result tag")?,
+                reason: reason.ok_or("The record has no Reason tag")?,
+            },
+        })
+    }
+}
+
+fn parse_moves(line: &str, moves: &mut Vec<Move>) -> Result<(), String> {
+    let mut tokens = line.split_whitespace();
+    while let Some(token) = tokens.next() {
+        if token.ends_with('.') && token[..token.len() - 1].parse::<usize>().is_ok() {
+            continue;
+        }
+        let (cell, mark) = match token.len().checked_sub(2).map(|at| token.split_at(at)) {
+            Some((cell, "??")) => (cell, Some(Mark::BLUNDER)),
+            Some((cell, "?!")) => (cell, Some(Mark::INACCURACY)),
+            _ => (token, None),
+        };
+        let annotation = match mark {
+            Some(mark) => {
+                let better = match (tokens.next(), tokens.next()) {
+                    (Some("{better"), Some(better)) => better.strip_suffix('}'),
+                    _ => None,
+                }
+                .ok_or_else(|| format!("'{}' needs a {{better <cell>}} comment", token))?;
+                Some(Annotation {
+                    mark,
+                    better: parse_cell(better)?,
+                })
+            }
+            None => None,
+        };
+        moves.push(Move {
+            field: parse_cell(cell)?,
+            annotation,
+        });
+    }
+    Ok(())
+}
+
+fn player_tag(player: Player) -> &'static str {
+    match player {
+        Player::ONE => "one",
+        Player::TWO => "two",
+    }
+}
+
+fn parse_player(value: &str) -> Result<Player, String> {
+    match value {
+        "one" => Ok(Player::ONE),
+        "two" => Ok(Player::TWO),
+        _ => Err(format!("'{}' is not a player, use one or two", value)),
+    }
+}
+
+fn score(result: GameResult) -> &'static str {
+    match result.winner {
+        Some(Player::ONE) => "1-0",
+        Some(Player::TWO) => "0-1",
+        None => "½-½",
+    }
+}
+
+fn parse_score(value: &str) -> Result<Option<Player>, String> {
+    match value {
+        "1-0" => Ok(Some(Player::ONE)),
+        "0-1" => Ok(Some(Player::TWO)),
+        "½-½" | "1/2-1/2" => Ok(None),
+        _ => Err(format!("'{}' is not a result like 1-0, 0-1 or ½-½", value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn record() -> GameRecord {
+        let result = GameResult {
+            winner: Some(Player::ONE),
+            reason: Reason::LINE,
+        };
+        GameRecord::new(
+            Player::ONE,
+            &[(0, 0), (0, 1), (1, 1), (2, 1), (2, 2)],
+            result,
+        )
+        .with_names(["alice".to_string(), "bob".to_string()])
+    }
+
+    #[test]
+    fn cells_have_names() {
+        assert_eq!("a1", cell_name((0, 0)));
+        assert_eq!("c2", cell_name((1, 2)));
+        assert_eq!(Ok((1, 2)), parse_cell("c2"));
+        assert!(parse_cell("2c").is_err());
+        assert!(parse_cell("a0").is_err());
+    }
+
+    #[test]
+    fn positions_can_be_read() {
+        let (board, player) = parse_position("x.o/.x./...").unwrap();
+        assert_eq!(Some(Player::ONE), board[0][0]);
+        assert_eq!(Some(Player::TWO), board[0][2]);
+        assert_eq!(Some(Player::ONE), board[1][1]);
+        assert_eq!(6, board.empty_cells().count());
+        assert_eq!(Player::TWO, player);
+        let (_, player) = parse_position("x../.../..o x").unwrap();
+        assert_eq!(Player::ONE, player);
+        assert!(parse_position("x../...").is_err());
+        assert!(parse_position("x../..../...").is_err());
+        assert!(parse_position("x../.q./...").is_err());
+        assert!(parse_position("x../.../... z").is_err());
+    }
+
+    #[test]
+    fn records_are_written_as_text() {
+        let record = record().with_annotations(vec![
+            None,
+            Some(Annotation {
+                mark: Mark::BLUNDER,
+                better: (1, 1),
+            }),
+        ]);
+        assert_eq!(
+            "[One \"alice\"]\n[Two \"bob\"]\n[First \"one\"]\n[Result \"1-0\"]\n\
+             [Reason \"line completed\"]\n\n1. a1 2. b1?? {better b2} 3. b2 4. b3 5. c3\n",
+            record.to_string()
+        );
+    }
+
+    #[test]
+    fn records_can_be_read_back() {
+        let record = record().with_annotations(vec![
+            None,
+            Some(Annotation {
+                mark: Mark::BLUNDER,
+                better: (1, 1),
+            }),
+            Some(Annotation {
+                mark: Mark::INACCURACY,
+                better: (0, 2),
+            }),
+        ]);
+        assert_eq!(Ok(record.clone()), record.to_string().parse());
+        let unnamed = GameRecord {
+            names: None,
+            first: Player::TWO,
+            ..record
+        };
+        assert_eq!(Ok(unnamed.clone()), unnamed.to_string().parse());
+    }
+
+    #[test]
+    fn broken_records_are_errors() {
+        assert!("1. a1 2. b2".parse::<GameRecord>().is_err());
+        assert!("[Result \"1-0\"]\n[Reason \"line completed\"]\n1. a1??"
+            .parse::<GameRecord>()
+            .is_err());
+        assert!("[Result \"2-0\"]\n[Reason \"line completed\"]"
+            .parse::<GameRecord>()
+            .is_err());
+    }
+}
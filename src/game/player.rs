@@ -1,14 +1,26 @@
 use std::fmt;
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
 pub enum Player {
+    #[default]
     ONE,
     TWO,
 }
 
-impl Default for Player {
-    fn default() -> Self {
-        Player::ONE
+impl Player {
+    pub fn other(self) -> Self {
+        match self {
+            Self::ONE => Self::TWO,
+            Self::TWO => Self::ONE,
+        }
+    }
+
+    /// 0 for Player ONE and 1 for Player TWO, for anything kept once per player.
+    pub fn index(self) -> usize {
+        match self {
+            Self::ONE => 0,
+            Self::TWO => 1,
+        }
     }
 }
 
@@ -26,10 +38,21 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn players_have_an_index_each() {
+        assert_eq!((0, 1), (Player::ONE.index(), Player::TWO.index()));
+    }
+
     #[test]
     fn can_represent_players() {
         let one = format!("{}", Player::ONE);
         let two = format!("{}", Player::TWO);
         assert_eq!(("🧠".to_string(), "🍺".to_string()), (one, two));
     }
+
+    #[test]
+    fn other_player_is_the_opponent() {
+        assert_eq!(Player::TWO, Player::ONE.other());
+        assert_eq!(Player::ONE, Player::TWO.other());
+    }
 }
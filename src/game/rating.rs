@@ -0,0 +1,361 @@
+use crate::game::player::Player;
+use crate::game::stats::Record;
+
+use std::collections::BTreeMap;
+use std::f64::consts::{LN_10, PI};
+use std::str::FromStr;
+
+const INITIAL_RATING: f64 = 1500.0;
+const INITIAL_UNCERTAINTY: f64 = 350.0;
+const INITIAL_VOLATILITY: f64 = 0.06;
+const ELO_K: f64 = 32.0;
+/// Constrains how much the volatility of a Glicko-2 rating may change.
+const GLICKO_TAU: f64 = 0.5;
+/// Converts between the Glicko and the Glicko-2 scale.
+const GLICKO_SCALE: f64 = 173.7178;
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum System {
+    #[default]
+    ELO,
+    GLICKO2,
+}
+
+impl FromStr for System {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "elo" => Ok(Self::ELO),
+            "glicko2" | "glicko-2" => Ok(Self::GLICKO2),
+            _ => Err(format!("Unknown rating system '{}'. Use elo or glicko2", s)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Rating {
+    pub value: f64,
+    /// One standard deviation. Shrinks the more games a player has played.
+    pub uncertainty: f64,
+    volatility: f64,
+}
+
+impl Default for Rating {
+    fn default() -> Self {
+        Rating {
+            value: INITIAL_RATING,
+            uncertainty: INITIAL_UNCERTAINTY,
+            volatility: INITIAL_VOLATILITY,
+        }
+    }
+}
+
+/// How one game changed the rating of a player.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Change {
+    pub played_at: u64,
+    pub opponent: String,
+    /// 1 for a win, 0.5 for a draw and 0 for a loss.
+    pub score: f64,
+    pub before: Rating,
+    pub after: Rating,
+}
+
+/// Ratings of everybody in the records. Replaying the records in order updates them game by game.
+#[derive(Debug)]
+pub struct Ratings {
+    system: System,
+    players: BTreeMap<String, (Rating, Vec<Change>)>,
+}
+
+impl Ratings {
+    pub fn from_records(system: System, records: &[Record]) -> Self {
+        let mut ratings = Ratings {
+            system,
+            players: BTreeMap::new(),
+        };
+        for record in records {
+            ratings.add(record);
+        }
+        ratings
+    }
+
+    fn add(&mut self, record: &Record) {
+        let [one, two] = &record.players;
+        let score_one = match record.result.winner {
+            Some(Player::ONE) => 1.0,
+            Some(Player::TWO) => 0.0,
+            None => 0.5,
+        };
+        let rating_one = self.rating(one);
+        let rating_two = self.rating(two);
+        let (after_one, after_two) = match self.system {
+            System::ELO => (
+                elo(rating_one, rating_two, score_one),
+                elo(rating_two, rating_one, 1.0 - score_one),
+            ),
+            System::GLICKO2 => (
+                glicko2(rating_one, &[(rating_two, score_one)]),
+                glicko2(rating_two, &[(rating_one, 1.0 - score_one)]),
+            ),
+        };
+        for (name, opponent, score, before, after) in [
+            (one, two, score_one, rating_one, after_one),
+            (two, one, 1.0 - score_one, rating_two, after_two),
+        ] {
+            let (rating, history) = self.players.entry(name.clone()).or_default();
+            *rating = after;
+            history.push(Change {
+                played_at: record.played_at,
+                opponent: opponent.clone(),
+                score,
+                before,
+                after,
+            });
+        }
+    }
+
+    /// The current rating of `name`. Unknown players have the initial rating.
+    pub fn rating(&self, name: &str) -> Rating {
+        self.players
+            .get(name)
+            .map(|(rating, _)| *rating)
+            .unwrap_or_default()
+    }
+
+    pub fn history(&self, name: &str) -> &[Change] {
+        self.players
+            .get(name)
+            .map_or(&[], |(_, history)| history.as_slice())
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        let mut ranked: Vec<(&String, &Rating, usize)> = self
+            .players
+            .iter()
+            .map(|(name, (rating, history))| (name, rating, history.len()))
+            .collect();
+        ranked.sort_by(|a, b| b.1.value.total_cmp(&a.1.value));
+        let mut lines = vec![
+            format!("Ratings ({:?})", self.system),
+            String::new(),
+            format!(
+                "{:>3}  {:<16} {:>6} {:>6} {:>5}",
+                "#", "Player", "Rating", "±", "Games"
+            ),
+        ];
+        for (rank, (name, rating, games)) in ranked.into_iter().enumerate() {
+            lines.push(format!(
+                "{:>3}  {:<16} {:>6.0} {:>6.0} {:>5}",
+                rank + 1,
+                name,
+                rating.value,
+                rating.uncertainty,
+                games
+            ));
+        }
+        lines
+    }
+
+    pub fn history_lines(&self, name: &str) -> Vec<String> {
+        let mut lines = vec![
+            format!("Rating history of {} ({:?})", name, self.system),
+            String::new(),
+        ];
+        for change in self.history(name) {
+            let result = match change.score {
+                score if score > 0.5 => "won against",
+                score if score < 0.5 => "lost against",
+                _ => "drew with",
+            };
+            lines.push(format!(
+                "{:>6.0} -> {:>6.0} ({:+4.0})  ± {:<4.0} {} {}",
+                change.before.value,
+                change.after.value,
+                change.after.value - change.before.value,
+                change.after.uncertainty,
+                result,
+                change.opponent
+            ));
+        }
+        if self.history(name).is_empty() {
+            lines.push(format!("{} has not played a rated game yet.", name));
+        }
+        lines
+    }
+}
+
+/// The expected score against an opponent `diff` rating points weaker.
+fn expected(diff: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-diff / 400.0))
+}
+
+/// Classic Elo. The uncertainty is the standard error that the games played so far allow.
+fn elo(rating: Rating, opponent: Rating, score: f64) -> Rating {
+    let expected = expected(rating.value - opponent.value);
+    let q = LN_10 / 400.0;
+    let information = 1.0 / rating.uncertainty.powi(2) + q * q * expected * (1.0 - expected);
+    Rating {
+        value: rating.value + ELO_K * (score - expected),
+        uncertainty: 1.0 / information.sqrt(),
+        volatility: rating.volatility,
+    }
+}
+
+/// Glicko-2 as described by Mark Glickman, with `games` as one rating period.
+fn glicko2(rating: Rating, games: &[(Rating, f64)]) -> Rating {
+    let g = |phi: f64| 1.0 / (1.0 + 3.0 * phi * phi / (PI * PI)).sqrt();
+    let mu = (rating.value - INITIAL_RATING) / GLICKO_SCALE;
+    let phi = rating.uncertainty / GLICKO_SCALE;
+    let sigma = rating.volatility;
+
+    let mut inverse_v = 0.0;
+    let mut improvement = 0.0;
+    for (opponent, score) in games {
+        let mu_j = (opponent.value - INITIAL_RATING) / GLICKO_SCALE;
+        let g_j = g(opponent.uncertainty / GLICKO_SCALE);
+        let e = 1.0 / (1.0 + (-g_j * (mu - mu_j)).exp());
+        inverse_v += g_j * g_j * e * (1.0 - e);
+        improvement += g_j * (score - e);
+    }
+    let v = 1.0 / inverse_v;
+    let delta = v * improvement;
+
+    // Find the new volatility with the Illinois algorithm
+    let a = (sigma * sigma).ln();
+    let f = |x: f64| {
+        let ex = x.exp();
+        ex * (delta * delta - phi * phi - v - ex) / (2.0 * (phi * phi + v + ex).powi(2))
+            - (x - a) / (GLICKO_TAU * GLICKO_TAU)
+    };
+    let mut big_a = a;
+    let mut big_b = if delta * delta > phi * phi + v {
+        (delta * delta - phi * phi - v).ln()
+    } else {
+        let mut k = 1.0;
+        while f(a - k * GLICKO_TAU) < 0.0 {
+            k += 1.0;
+        }
+        a - k * GLICKO_TAU
+    };
+    let mut f_a = f(big_a);
+    let mut f_b = f(big_b);
+    while (big_b - big_a).abs() > 0.000_001 {
+        let big_c = big_a + (big_a - big_b) * f_a / (f_b - f_a);
+        let f_c = f(big_c);
+        if f_c * f_b <= 0.0 {
+            big_a = big_b;
+            f_a = f_b;
+        } else {
+            f_a /= 2.0;
+        }
+        big_b = big_c;
+        f_b = f_c;
+    }
+    let new_sigma = (big_a / 2.0).exp();
+
+    let pre_phi = (phi * phi + new_sigma * new_sigma).sqrt();
+    let new_phi = 1.0 / (1.0 / (pre_phi * pre_phi) + 1.0 / v).sqrt();
+    let new_mu = mu + new_phi * new_phi * improvement;
+    Rating {
+        value: new_mu * GLICKO_SCALE + INITIAL_RATING,
+        uncertainty: new_phi * GLICKO_SCALE,
+        volatility: new_sigma,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::game::board::{GameResult, Reason};
+
+    fn record(one: &str, two: &str, winner: Option<Player>) -> Record {
+        Record::new(
+            [one.to_string(), two.to_string()],
+            GameResult {
+                winner,
+                reason: Reason::LINE,
+            },
+            7,
+        )
+    }
+
+    fn rating(value: f64, uncertainty: f64) -> Rating {
+        Rating {
+            value,
+            uncertainty,
+            volatility: INITIAL_VOLATILITY,
+        }
+    }
+
+    #[test]
+    fn elo_moves_points_from_loser_to_winner() {
+        let records = [record("ada", "bob", Some(Player::ONE))];
+        let ratings = Ratings::from_records(System::ELO, &records);
+        assert_eq!(1516.0, ratings.rating("ada").value);
+        assert_eq!(1484.0, ratings.rating("bob").value);
+        assert!(ratings.rating("ada").uncertainty < INITIAL_UNCERTAINTY);
+    }
+
+    #[test]
+    fn elo_draw_between_equals_changes_nothing() {
+        let records = [record("ada", "bob", None)];
+        let ratings = Ratings::from_records(System::ELO, &records);
+        assert_eq!(1500.0, ratings.rating("ada").value);
+        assert_eq!(1500.0, ratings.rating("bob").value);
+    }
+
+    #[test]
+    fn uncertainty_shrinks_with_every_game() {
+        let records = vec![record("ada", "bob", None); 20];
+        let ratings = Ratings::from_records(System::GLICKO2, &records);
+        let history = ratings.history("ada");
+        assert_eq!(20, history.len());
+        assert!(history
+            .windows(2)
+            .all(|pair| pair[1].after.uncertainty < pair[0].after.uncertainty));
+    }
+
+    #[test]
+    fn glicko2_matches_glickmans_example() {
+        let games = [
+            (rating(1400.0, 30.0), 1.0),
+            (rating(1550.0, 100.0), 0.0),
+            (rating(1700.0, 300.0), 0.0),
+        ];
+        let after = glicko2(rating(1500.0, 200.0), &games);
+        assert!((after.value - 1464.06).abs() < 0.01);
+        assert!((after.uncertainty - 151.52).abs() < 0.01);
+        assert!((after.volatility - 0.05999).abs() < 0.00001);
+    }
+
+    #[test]
+    fn history_lists_every_rated_game() {
+        let records = [
+            record("ada", "bob", Some(Player::ONE)),
+            record("cyd", "ada", Some(Player::ONE)),
+        ];
+        let ratings = Ratings::from_records(System::ELO, &records);
+        let history = ratings.history("ada");
+        assert_eq!(2, history.len());
+        assert_eq!(
+            ("bob", 1.0),
+            (history[0].opponent.as_str(), history[0].score)
+        );
+        assert_eq!(
+            ("cyd", 0.0),
+            (history[1].opponent.as_str(), history[1].score)
+        );
+        assert_eq!(history[0].after, history[1].before);
+        assert!(ratings.history("eve").is_empty());
+    }
+
+    #[test]
+    fn can_parse_systems() {
+        assert_eq!(Ok(System::GLICKO2), "glicko2".parse());
+        assert_eq!(Ok(System::ELO), "elo".parse());
+        assert!("trueskill".parse::<System>().is_err());
+    }
+}
@@ -0,0 +1,433 @@
+use crate::game::board::{GameResult, Reason, Rules, Shape};
+use crate::game::clock::TimeControl;
+use crate::game::notation::GameRecord;
+use crate::game::player::Player;
+use crate::game::rating::System;
+use crate::game::{offer_analysis, print_lines, Game};
+
+use std::str::FromStr;
+
+/// How many sudden-death games a `TieBreak::DECIDER` plays at most.
+const DECIDER_LIMIT: usize = 5;
+
+/// Who moves first in the next game of a series.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Opening {
+    ALTERNATE,
+    /// The loser of the last game starts. After a draw the players alternate.
+    LOSER,
+}
+
+impl FromStr for Opening {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "alternate" => Ok(Self::ALTERNATE),
+            "loser" => Ok(Self::LOSER),
+            _ => Err(format!(
+                "Unknown opening rule '{}'. Use alternate or loser",
+                s
+            )),
+        }
+    }
+}
+
+/// What happens if both players have the same score after all games.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TieBreak {
+    /// Sudden-death games until one of them is won.
+    DECIDER,
+    /// More wins with the second move, which is the harder side.
+    SECOND,
+    /// The match stays drawn.
+    NONE,
+}
+
+impl FromStr for TieBreak {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "decider" => Ok(Self::DECIDER),
+            "second" => Ok(Self::SECOND),
+            "none" => Ok(Self::NONE),
+            _ => Err(format!(
+                "Unknown tie-break '{}'. Use decider, second or none",
+                s
+            )),
+        }
+    }
+}
+
+/// A best-of-N match between the same two players.
+#[derive(Debug)]
+pub struct Series {
+    best_of: usize,
+    opening: Opening,
+    tie_break: TieBreak,
+    time_control: Option<TimeControl>,
+    names: Option<[String; 2]>,
+    rating_system: System,
+    shape: Shape,
+    rules: Rules,
+    /// Who started each game and how it ended.
+    games: Vec<(Player, GameResult)>,
+}
+
+impl Series {
+    pub fn new(best_of: usize) -> Self {
+        Series {
+            best_of,
+            opening: Opening::ALTERNATE,
+            tie_break: TieBreak::DECIDER,
+            time_control: None,
+            names: None,
+            rating_system: System::default(),
+            shape: Shape::default(),
+            rules: Rules::default(),
+            games: Vec::new(),
+        }
+    }
+
+    pub fn with_opening(mut self, opening: Opening) -> Self {
+        self.opening = opening;
+        self
+    }
+
+    pub fn with_tie_break(mut self, tie_break: TieBreak) -> Self {
+        self.tie_break = tie_break;
+        self
+    }
+
+    pub fn with_time_control(mut self, control: TimeControl) -> Self {
+        self.time_control = Some(control);
+        self
+    }
+
+    pub fn with_names(mut self, names: [String; 2]) -> Self {
+        self.names = Some(names);
+        self
+    }
+
+    pub fn with_rating_system(mut self, system: System) -> Self {
+        self.rating_system = system;
+        self
+    }
+
+    pub fn with_shape(mut self, shape: Shape) -> Self {
+        self.shape = shape;
+        self
+    }
+
+    pub fn with_rules(mut self, rules: Rules) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    /// Plays games until the match is decided. Returns the winner of the match.
+    pub fn play(&mut self) -> Option<Player> {
+        let mut records: Vec<(usize, GameRecord)> = Vec::new();
+        let winner = loop {
+            if let Some(winner) = self.get_winner() {
+                break winner;
+            }
+            let first = self.next_first();
+            print_lines([
+                format!(
+                    "{} (best of {})",
+                    self.label(self.games.len()),
+                    self.best_of
+                ),
+                format!("Score: {}", self.score_line()),
+            ]);
+            let mut game = Game::new()
+                .with_first_player(first)
+                .with_rating_system(self.rating_system)
+                .with_shape(self.shape)
+                .with_rules(self.rules);
+            if let Some(control) = self.time_control {
+                game = game.with_time_control(control);
+            }
+            if let Some(names) = &self.names {
+                game = game.with_names(names.clone());
+            }
+            let result = game.play_game();
+            if let Some(record) = game.annotated_record(result) {
+                records.push((self.games.len() + 1, record));
+            }
+            self.games.push((first, result));
+        };
+
+        print_lines(self.summary(winner));
+        offer_analysis(&records);
+        print_lines(["Thanks for playing. Come back soon!"]);
+        winner
+    }
+
+    fn next_first(&self) -> Player {
+        match (self.opening, self.games.last()) {
+            (_, None) => Player::ONE,
+            (
+                Opening::LOSER,
+                Some((
+                    _,
+                    GameResult {
+                        winner: Some(winner),
+                        ..
+                    },
+                )),
+            ) => winner.other(),
+            (_, Some((first, _))) => first.other(),
+        }
+    }
+
+    /// The score of `player` in half points, so a draw counts as 1 and a win as 2.
+    fn half_points(&self, player: Player, games: &[(Player, GameResult)]) -> usize {
+        games
+            .iter()
+            .map(|(_, result)| match result.winner {
+                Some(winner) if winner == player => 2,
+                Some(_) => 0,
+                None if result.reason == Reason::ABANDONMENT => 0,
+                None => 1,
+            })
+            .sum()
+    }
+
+    /// `None` while the match goes on, otherwise the winner of the match if there is one.
+    fn get_winner(&self) -> Option<Option<Player>> {
+        if let Some((_, result)) = self.games.last() {
+            if result.reason == Reason::ABANDONMENT {
+                return Some(None);
+            }
+        }
+        let regular = &self.games[..self.games.len().min(self.best_of)];
+        let one = self.half_points(Player::ONE, regular);
+        let two = self.half_points(Player::TWO, regular);
+        // More than half of all points can not be caught up anymore
+        if one > self.best_of {
+            return Some(Some(Player::ONE));
+        }
+        if two > self.best_of {
+            return Some(Some(Player::TWO));
+        }
+        if regular.len() < self.best_of {
+            return None;
+        }
+        if one != two {
+            return Some(Some(if one > two { Player::ONE } else { Player::TWO }));
+        }
+        match self.tie_break {
+            TieBreak::NONE => Some(None),
+            TieBreak::SECOND => {
+                let second_wins = |player| {
+                    regular
+                        .iter()
+                        .filter(|(first, result)| result.winner == Some(player) && *first != player)
+                        .count()
+                };
+                let (one, two) = (second_wins(Player::ONE), second_wins(Player::TWO));
+                Some(match one.cmp(&two) {
+                    std::cmp::Ordering::Greater => Some(Player::ONE),
+                    std::cmp::Ordering::Less => Some(Player::TWO),
+                    std::cmp::Ordering::Equal => None,
+                })
+            }
+            TieBreak::DECIDER => {
+                let deciders = &self.games[self.best_of..];
+                match deciders.iter().find_map(|(_, result)| result.winner) {
+                    Some(winner) => Some(Some(winner)),
+                    None if deciders.len() >= DECIDER_LIMIT => Some(None),
+                    None => None,
+                }
+            }
+        }
+    }
+
+    fn score_line(&self) -> String {
+        let regular = &self.games[..self.games.len().min(self.best_of)];
+        format!(
+            "Player ONE {} - {} Player TWO",
+            HalfPoints(self.half_points(Player::ONE, regular)),
+            HalfPoints(self.half_points(Player::TWO, regular)),
+        )
+    }
+
+    fn label(&self, idx: usize) -> String {
+        if idx < self.best_of {
+            format!("Game {}", idx + 1)
+        } else {
+            format!("Decider {}", idx + 1 - self.best_of)
+        }
+    }
+
+    fn summary(&self, winner: Option<Player>) -> Vec<String> {
+        let mut lines = vec!["Match summary:".to_string(), String::new()];
+        for (idx, (first, result)) in self.games.iter().enumerate() {
+            lines.push(format!(
+                "{:<11} {:<22} Player {:?} started",
+                self.label(idx),
+                result.to_string(),
+                first
+            ));
+        }
+        lines.push(String::new());
+        lines.push(format!("Final score: {}", self.score_line()));
+        lines.push(match winner {
+            Some(winner) => format!("Player {:?} wins the match!", winner),
+            None => "The match ends without a winner.".to_string(),
+        });
+        lines
+    }
+}
+
+struct HalfPoints(usize);
+
+impl std::fmt::Display for HalfPoints {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.0 / 2, self.0 % 2) {
+            (points, 0) => write!(f, "{}", points),
+            (0, _) => write!(f, "½"),
+            (points, _) => write!(f, "{}½", points),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn won(winner: Player) -> GameResult {
+        GameResult {
+            winner: Some(winner),
+            reason: Reason::LINE,
+        }
+    }
+
+    fn drawn() -> GameResult {
+        GameResult {
+            winner: None,
+            reason: Reason::FULL,
+        }
+    }
+
+    fn record(series: &mut Series, results: &[GameResult]) {
+        for result in results {
+            let first = series.next_first();
+            series.games.push((first, *result));
+        }
+    }
+
+    #[test]
+    fn first_player_alternates() {
+        let mut series = Series::new(3);
+        assert_eq!(Player::ONE, series.next_first());
+        record(&mut series, &[won(Player::ONE)]);
+        assert_eq!(Player::TWO, series.next_first());
+        record(&mut series, &[drawn()]);
+        assert_eq!(Player::ONE, series.next_first());
+    }
+
+    #[test]
+    fn loser_starts_the_next_game() {
+        let mut series = Series::new(5).with_opening(Opening::LOSER);
+        record(&mut series, &[won(Player::TWO)]);
+        assert_eq!(Player::ONE, series.next_first());
+        record(&mut series, &[won(Player::ONE)]);
+        assert_eq!(Player::TWO, series.next_first());
+        // after a draw the players alternate
+        record(&mut series, &[drawn()]);
+        assert_eq!(Player::ONE, series.next_first());
+    }
+
+    #[test]
+    fn match_is_decided_once_the_lead_can_not_be_caught() {
+        let mut series = Series::new(3);
+        record(&mut series, &[won(Player::TWO)]);
+        assert_eq!(None, series.get_winner());
+        record(&mut series, &[won(Player::TWO)]);
+        assert_eq!(Some(Some(Player::TWO)), series.get_winner());
+    }
+
+    #[test]
+    fn higher_score_wins_after_all_games() {
+        let mut series = Series::new(3);
+        record(&mut series, &[won(Player::ONE), drawn(), drawn()]);
+        assert_eq!(Some(Some(Player::ONE)), series.get_winner());
+        assert_eq!("Player ONE 2 - 1 Player TWO", series.score_line());
+
+        let mut series = Series::new(3);
+        record(&mut series, &[drawn(), won(Player::TWO), won(Player::TWO)]);
+        assert_eq!("Player ONE ½ - 2½ Player TWO", series.score_line());
+    }
+
+    #[test]
+    fn tie_is_broken_by_a_decider() {
+        let mut series = Series::new(2);
+        record(&mut series, &[won(Player::ONE), won(Player::TWO)]);
+        assert_eq!(None, series.get_winner());
+        record(&mut series, &[drawn()]);
+        assert_eq!(None, series.get_winner());
+        record(&mut series, &[won(Player::TWO)]);
+        assert_eq!(Some(Some(Player::TWO)), series.get_winner());
+    }
+
+    #[test]
+    fn deciders_are_limited() {
+        let mut series = Series::new(1);
+        record(&mut series, &[drawn(); 1 + DECIDER_LIMIT]);
+        assert_eq!(Some(None), series.get_winner());
+    }
+
+    #[test]
+    fn tie_is_broken_by_wins_with_the_second_move() {
+        let mut series = Series::new(4).with_tie_break(TieBreak::SECOND);
+        // ONE starts games 1 and 3, TWO starts games 2 and 4
+        record(
+            &mut series,
+            &[
+                won(Player::ONE),
+                won(Player::ONE),
+                drawn(),
+                won(Player::TWO),
+            ],
+        );
+        assert_eq!("Player ONE 2½ - 1½ Player TWO", series.score_line());
+
+        let mut series = Series::new(2).with_tie_break(TieBreak::SECOND);
+        record(&mut series, &[won(Player::TWO), won(Player::TWO)]);
+        assert_eq!(Some(Some(Player::TWO)), series.get_winner());
+
+        let mut series = Series::new(2).with_tie_break(TieBreak::SECOND);
+        record(&mut series, &[won(Player::ONE), won(Player::TWO)]);
+        assert_eq!(Some(None), series.get_winner());
+    }
+
+    #[test]
+    fn tie_can_stay_a_draw() {
+        let mut series = Series::new(2).with_tie_break(TieBreak::NONE);
+        record(&mut series, &[drawn(), drawn()]);
+        assert_eq!(Some(None), series.get_winner());
+    }
+
+    #[test]
+    fn abandoning_a_game_ends_the_match() {
+        let mut series = Series::new(5);
+        let abandoned = GameResult {
+            winner: None,
+            reason: Reason::ABANDONMENT,
+        };
+        record(&mut series, &[won(Player::ONE), abandoned]);
+        assert_eq!(Some(None), series.get_winner());
+        assert_eq!("Player ONE 1 - 0 Player TWO", series.score_line());
+    }
+
+    #[test]
+    fn can_parse_rules() {
+        assert_eq!(Ok(Opening::LOSER), "loser".parse());
+        assert_eq!(Ok(TieBreak::SECOND), "second".parse());
+        assert!("coin".parse::<TieBreak>().is_err());
+    }
+}
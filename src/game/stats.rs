@@ -0,0 +1,448 @@
+use crate::game::board::{GameResult, Reason, Rules, Shape};
+use crate::game::player::Player;
+
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+const FILE_NAME: &str = "games.tsv";
+
+/// One finished game between two named players.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Record {
+    /// Seconds since the unix epoch.
+    pub played_at: u64,
+    /// The names of Player ONE and Player TWO.
+    pub players: [String; 2],
+    pub result: GameResult,
+    pub moves: usize,
+    /// The board and rules the game was played with, see [`ruleset`], or the variant.
+    pub ruleset: String,
+}
+
+/// Names a board and its rules, like `6x7x4 gravity`. Statistics and ratings only
+/// compare games of the same ruleset.
+pub fn ruleset(shape: Shape, rules: Rules) -> String {
+    let mut name = shape.to_string();
+    for (rule, on) in [
+        ("gravity", rules.gravity),
+        ("misere", rules.misere),
+        ("wild", rules.wild),
+        ("wrap", rules.wrap),
+    ] {
+        if on {
+            name.push(' ');
+            name.push_str(rule);
+        }
+    }
+    name
+}
+
+impl Record {
+    pub fn new(players: [String; 2], result: GameResult, moves: usize) -> Self {
+        let played_at = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |since_epoch| since_epoch.as_secs());
+        Record {
+            played_at,
+            players,
+            result,
+            moves,
+            ruleset: ruleset(Shape::default(), Rules::default()),
+        }
+    }
+
+    pub fn with_ruleset(mut self, ruleset: String) -> Self {
+        self.ruleset = ruleset;
+        self
+    }
+
+    fn to_line(&self) -> String {
+        let winner = match self.result.winner {
+            Some(Player::ONE) => "one",
+            Some(Player::TWO) => "two",
+            None => "none",
+        };
+        format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            self.played_at,
+            self.players[0],
+            self.players[1],
+            winner,
+            self.result.reason,
+            self.moves,
+            self.ruleset
+        )
+    }
+
+    /// Lines written before the ruleset was recorded are classic games.
+    fn from_line(line: &str) -> Option<Self> {
+        let mut fields: Vec<&str> = line.split('\t').collect();
+        let classic = ruleset(Shape::default(), Rules::default());
+        if fields.len() == 6 {
+            fields.push(&classic);
+        }
+        if let [played_at, one, two, winner, reason, moves, ruleset] = fields[..] {
+            let winner = match winner {
+                "one" => Some(Player::ONE),
+                "two" => Some(Player::TWO),
+                "none" => None,
+                _ => return None,
+            };
+            Some(Record {
+                played_at: played_at.parse().ok()?,
+                players: [one.to_string(), two.to_string()],
+                result: GameResult {
+                    winner,
+                    reason: reason.parse().ok()?,
+                },
+                moves: moves.parse().ok()?,
+                ruleset: ruleset.to_string(),
+            })
+        } else {
+            None
+        }
+    }
+
+    /// The outcome from the view of `name`, if `name` played in this game.
+    fn outcome_for(&self, name: &str) -> Option<Outcome> {
+        let player = match name {
+            _ if self.players[0] == name => Player::ONE,
+            _ if self.players[1] == name => Player::TWO,
+            _ => return None,
+        };
+        Some(match self.result.winner {
+            Some(winner) if winner == player => Outcome::WIN,
+            Some(_) => Outcome::LOSS,
+            None => Outcome::DRAW,
+        })
+    }
+}
+
+/// Every finished game, one line each, in a file in the user's data directory.
+#[derive(Debug)]
+pub struct Store {
+    path: PathBuf,
+}
+
+impl Store {
+    pub fn open() -> io::Result<Self> {
+        let dir = data_dir().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "No data directory for statistics")
+        })?;
+        fs::create_dir_all(&dir)?;
+        Ok(Store::at(dir.join(FILE_NAME)))
+    }
+
+    pub fn at(path: impl AsRef<Path>) -> Self {
+        Store {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Abandoned games are not counted.
+    pub fn record(&self, record: &Record) -> io::Result<()> {
+        if record.result.reason == Reason::ABANDONMENT {
+            return Ok(());
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", record.to_line())
+    }
+
+    /// All records in the order they were played. Lines that can't be read are skipped.
+    pub fn records(&self) -> io::Result<Vec<Record>> {
+        match fs::read_to_string(&self.path) {
+            Ok(content) => Ok(content.lines().filter_map(Record::from_line).collect()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The records of the games played with `ruleset`.
+    pub fn records_of(&self, ruleset: &str) -> io::Result<Vec<Record>> {
+        let mut records = self.records()?;
+        records.retain(|record| record.ruleset == ruleset);
+        Ok(records)
+    }
+}
+
+/// `$XDG_DATA_HOME/tictactoe`, falling back to the platform's usual place.
+fn data_dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("XDG_DATA_HOME") {
+        return Some(PathBuf::from(dir).join("tictactoe"));
+    }
+    if cfg!(windows) {
+        return env::var_os("APPDATA").map(|dir| PathBuf::from(dir).join("tictactoe"));
+    }
+    let home = PathBuf::from(env::var_os("HOME")?);
+    if cfg!(target_os = "macos") {
+        Some(home.join("Library/Application Support/tictactoe"))
+    } else {
+        Some(home.join(".local/share/tictactoe"))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Outcome {
+    WIN,
+    DRAW,
+    LOSS,
+}
+
+impl Outcome {
+    fn letter(self) -> char {
+        match self {
+            Self::WIN => 'W',
+            Self::DRAW => 'D',
+            Self::LOSS => 'L',
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Stats {
+    pub name: String,
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+    /// The outcome of the last game and how often it happened in a row.
+    streak: Option<(Outcome, usize)>,
+    pub longest_win_streak: usize,
+    moves: usize,
+}
+
+impl Stats {
+    pub fn of(name: &str, records: &[Record]) -> Self {
+        let mut stats = Stats {
+            name: name.to_string(),
+            wins: 0,
+            draws: 0,
+            losses: 0,
+            streak: None,
+            longest_win_streak: 0,
+            moves: 0,
+        };
+        for record in records {
+            if let Some(outcome) = record.outcome_for(name) {
+                stats.add(outcome, record.moves);
+            }
+        }
+        stats
+    }
+
+    fn add(&mut self, outcome: Outcome, moves: usize) {
+        match outcome {
+            Outcome::WIN => self.wins += 1,
+            Outcome::DRAW => self.draws += 1,
+            Outcome::LOSS => self.losses += 1,
+        }
+        self.moves += moves;
+        self.streak = match self.streak {
+            Some((last, count)) if last == outcome => Some((last, count + 1)),
+            _ => Some((outcome, 1)),
+        };
+        if let Some((Outcome::WIN, count)) = self.streak {
+            self.longest_win_streak = self.longest_win_streak.max(count);
+        }
+    }
+
+    pub fn games(&self) -> usize {
+        self.wins + self.draws + self.losses
+    }
+
+    /// A win counts 1 point, a draw half a point.
+    pub fn points(&self) -> f64 {
+        self.wins as f64 + self.draws as f64 / 2.0
+    }
+
+    pub fn average_moves(&self) -> f64 {
+        match self.games() {
+            0 => 0.0,
+            games => self.moves as f64 / games as f64,
+        }
+    }
+
+    /// The current streak, e.g. `W3` for three wins in a row.
+    pub fn streak(&self) -> String {
+        match self.streak {
+            Some((outcome, count)) => format!("{}{}", outcome.letter(), count),
+            None => "-".to_string(),
+        }
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        vec![
+            format!("Statistics of {}", self.name),
+            String::new(),
+            format!("Games:               {}", self.games()),
+            format!("Wins:                {}", self.wins),
+            format!("Draws:               {}", self.draws),
+            format!("Losses:              {}", self.losses),
+            format!("Current streak:      {}", self.streak()),
+            format!("Longest win streak:  {}", self.longest_win_streak),
+            format!("Average game length: {:.1} moves", self.average_moves()),
+        ]
+    }
+}
+
+/// Everybody who ever played, best first.
+pub fn leaderboard(records: &[Record]) -> Vec<Stats> {
+    let mut names: Vec<&str> = records
+        .iter()
+        .flat_map(|record| record.players.iter().map(String::as_str))
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+    let mut board: Vec<Stats> = names
+        .into_iter()
+        .map(|name| Stats::of(name, records))
+        .collect();
+    board.sort_by(|a, b| {
+        b.points()
+            .total_cmp(&a.points())
+            .then(a.games().cmp(&b.games()))
+            .then(a.name.cmp(&b.name))
+    });
+    board
+}
+
+pub fn leaderboard_lines(records: &[Record]) -> Vec<String> {
+    let mut lines = vec![
+        "Leaderboard".to_string(),
+        String::new(),
+        format!(
+            "{:>3}  {:<16} {:>5} {:>4} {:>4} {:>4} {:>7} {:>6}",
+            "#", "Player", "Games", "W", "D", "L", "Points", "Streak"
+        ),
+    ];
+    for (rank, stats) in leaderboard(records).iter().enumerate() {
+        lines.push(format!(
+            "{:>3}  {:<16} {:>5} {:>4} {:>4} {:>4} {:>7.1} {:>6}",
+            rank + 1,
+            stats.name,
+            stats.games(),
+            stats.wins,
+            stats.draws,
+            stats.losses,
+            stats.points(),
+            stats.streak()
+        ));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn record(one: &str, two: &str, winner: Option<Player>, moves: usize) -> Record {
+        let reason = if winner.is_some() {
+            Reason::LINE
+        } else {
+            Reason::FULL
+        };
+        Record::new(
+            [one.to_string(), two.to_string()],
+            GameResult { winner, reason },
+            moves,
+        )
+    }
+
+    #[test]
+    fn records_survive_a_round_trip_through_a_line() {
+        let record = record("ada", "bob", Some(Player::TWO), 7);
+        let line = record.to_line();
+        assert!(line.ends_with("\tada\tbob\ttwo\tline completed\t7\t3x3x3"));
+        assert_eq!(Some(record.clone()), Record::from_line(&line));
+        assert_eq!(None, Record::from_line("not a record"));
+
+        let old = line.trim_end_matches("\t3x3x3");
+        assert_eq!(Some(record), Record::from_line(old));
+    }
+
+    #[test]
+    fn rulesets_name_the_board_and_its_rules() {
+        let connect_four = Rules {
+            gravity: true,
+            ..Rules::default()
+        };
+        assert_eq!("3x3x3", ruleset(Shape::default(), Rules::default()));
+        assert_eq!(
+            "6x7x4 gravity",
+            ruleset("6x7x4".parse().unwrap(), connect_four)
+        );
+    }
+
+    #[test]
+    fn stats_count_outcomes_and_streaks() {
+        let records = [
+            record("ada", "bob", Some(Player::ONE), 5),
+            record("bob", "ada", Some(Player::TWO), 6),
+            record("ada", "bob", None, 9),
+            record("ada", "cyd", Some(Player::ONE), 7),
+            record("ada", "bob", Some(Player::ONE), 5),
+            record("cyd", "ada", Some(Player::TWO), 6),
+        ];
+        let ada = Stats::of("ada", &records);
+        assert_eq!((5, 1, 0), (ada.wins, ada.draws, ada.losses));
+        assert_eq!("W3", ada.streak());
+        assert_eq!(3, ada.longest_win_streak);
+        assert_eq!(38.0 / 6.0, ada.average_moves());
+
+        let bob = Stats::of("bob", &records);
+        assert_eq!((0, 1, 3), (bob.wins, bob.draws, bob.losses));
+        assert_eq!("L1", bob.streak());
+
+        let nobody = Stats::of("eve", &records);
+        assert_eq!(
+            ("-".to_string(), 0.0),
+            (nobody.streak(), nobody.average_moves())
+        );
+    }
+
+    #[test]
+    fn leaderboard_is_sorted_by_points() {
+        let records = [
+            record("ada", "bob", Some(Player::TWO), 5),
+            record("cyd", "bob", None, 9),
+            record("ada", "cyd", Some(Player::ONE), 7),
+        ];
+        let names: Vec<String> = leaderboard(&records)
+            .into_iter()
+            .map(|stats| stats.name)
+            .collect();
+        assert_eq!(vec!["bob", "ada", "cyd"], names);
+    }
+
+    #[test]
+    fn store_appends_and_reads_records() {
+        let path = env::temp_dir().join(format!("tictactoe-stats-{}.tsv", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let store = Store::at(&path);
+        assert_eq!(Vec::<Record>::new(), store.records().unwrap());
+
+        let won = record("ada", "bob", Some(Player::ONE), 5);
+        let abandoned = Record::new(
+            ["ada".to_string(), "bob".to_string()],
+            GameResult {
+                winner: None,
+                reason: Reason::ABANDONMENT,
+            },
+            2,
+        );
+        let wild = record("ada", "bob", None, 9).with_ruleset("3x3x3 wild".to_string());
+        store.record(&won).unwrap();
+        store.record(&abandoned).unwrap();
+        store.record(&wild).unwrap();
+        assert_eq!(vec![won.clone(), wild.clone()], store.records().unwrap());
+        assert_eq!(vec![won], store.records_of("3x3x3").unwrap());
+        assert_eq!(vec![wild], store.records_of("3x3x3 wild").unwrap());
+        let _ = fs::remove_file(&path);
+    }
+}
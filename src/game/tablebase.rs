@@ -0,0 +1,337 @@
+use crate::game::analysis::Value;
+use crate::game::board::{Board, BoardState, Rules, Shape};
+use crate::game::player::Player;
+
+use std::collections::{BTreeMap, HashMap};
+
+/// Every position of the classic board that can come up when Player ONE moves first, up to
+/// symmetry, with its value for the player to move. Each entry is three bytes: the position
+/// as a little endian base 3 number (empty 0, ONE 1, TWO 2, `a1` is the lowest digit), then
+/// the value. Entries are sorted by position. `generate` writes it.
+static TABLE: &[u8] = include_bytes!("tablebase.bin");
+
+const SIZE: usize = 3;
+const ENTRY: usize = 3;
+
+/// Maps a cell to where a rotation or reflection of the board moves it.
+type Symmetry = fn(usize, usize) -> (usize, usize);
+
+/// The perfect-play value of the position for `player`, who is about to move.
+/// `None` if the position can't come up in a game on the classic board.
+pub fn lookup(board: &Board, player: Player) -> Option<Value> {
+    let swap = swap_players(board, player)?;
+    let key = canonical(index(board, swap)?);
+    let (mut low, mut high) = (0, TABLE.len() / ENTRY);
+    while low < high {
+        let mid = (low + high) / 2;
+        let entry = &TABLE[mid * ENTRY..(mid + 1) * ENTRY];
+        let at = u16::from_le_bytes([entry[0], entry[1]]);
+        if at == key {
+            return Some(decode(entry[2]));
+        } else if at < key {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    None
+}
+
+/// The value of every legal move for `player`, read from the table. Scored like a search
+/// would score them, `None` if any position isn't in the table.
+pub fn move_values(board: &Board, player: Player) -> Option<Vec<((usize, usize), Value)>> {
+    if board.get_board_state() != BoardState::ONGOING {
+        return Some(Vec::new());
+    }
+    board
+        .empty_cells()
+        .map(|field| {
+            let mut next = board.clone();
+            next.set_value(player, field).ok()?;
+            Some((field, one_move_earlier(lookup(&next, player.other())?)))
+        })
+        .collect()
+}
+
+/// Whether the position is only reachable with Player TWO moving first, so that the table
+/// has it with the players swapped. `None` if it isn't reachable either way.
+fn swap_players(board: &Board, player: Player) -> Option<bool> {
+    if board.get_shape() != Shape::default() || board.get_rules() != Rules::default() {
+        return None;
+    }
+    let count = |owner| {
+        board
+            .iter()
+            .flatten()
+            .filter(|&&cell| cell == Some(owner))
+            .count()
+    };
+    let (one, two) = (count(Player::ONE), count(Player::TWO));
+    match player {
+        Player::ONE if one == two => Some(false),
+        Player::ONE if two == one + 1 => Some(true),
+        Player::TWO if one == two + 1 => Some(false),
+        Player::TWO if one == two => Some(true),
+        _ => None,
+    }
+}
+
+fn index(board: &Board, swap: bool) -> Option<u16> {
+    if board.get_shape() != Shape::default() {
+        return None;
+    }
+    let mut index = 0;
+    for cell in board.iter().flatten().rev() {
+        let digit = match (cell, swap) {
+            (None, _) => 0,
+            (Some(Player::ONE), false) | (Some(Player::TWO), true) => 1,
+            _ => 2,
+        };
+        index = index * 3 + digit;
+    }
+    Some(index)
+}
+
+/// The smallest index among the 8 rotations and reflections of the position.
+fn canonical(index: u16) -> u16 {
+    let mut digits = [0; SIZE * SIZE];
+    let mut rest = index;
+    for digit in digits.iter_mut() {
+        *digit = rest % 3;
+        rest /= 3;
+    }
+    const LAST: usize = SIZE - 1;
+    let symmetries: [Symmetry; 8] = [
+        |row, col| (row, col),
+        |row, col| (col, LAST - row),
+        |row, col| (LAST - row, LAST - col),
+        |row, col| (LAST - col, row),
+        |row, col| (row, LAST - col),
+        |row, col| (LAST - row, col),
+        |row, col| (col, row),
+        |row, col| (LAST - col, LAST - row),
+    ];
+    symmetries
+        .iter()
+        .map(|symmetry| {
+            let mut index = 0;
+            for cell in (0..SIZE * SIZE).rev() {
+                let (row, col) = symmetry(cell / SIZE, cell % SIZE);
+                index = index * 3 + digits[row * SIZE + col];
+            }
+            index
+        })
+        .min()
+        .expect("There are 8 symmetries")
+}
+
+/// Turns the value for the player to move into the value for the player who moved before.
+fn one_move_earlier(value: Value) -> Value {
+    match value {
+        Value::WIN(moves) => Value::LOSS(moves + 1),
+        Value::DRAW => Value::DRAW,
+        Value::LOSS(moves) => Value::WIN(moves + 1),
+    }
+}
+
+fn encode(value: Value) -> u8 {
+    match value {
+        Value::DRAW => 0,
+        Value::WIN(moves) => 1 | (moves as u8) << 2,
+        Value::LOSS(moves) => 2 | (moves as u8) << 2,
+    }
+}
+
+fn decode(byte: u8) -> Value {
+    let moves = (byte >> 2) as usize;
+    match byte & 0b11 {
+        1 => Value::WIN(moves),
+        2 => Value::LOSS(moves),
+        _ => Value::DRAW,
+    }
+}
+
+/// Plays every game from the empty board and solves every position on the way.
+/// Keyed by index, so positions that only differ by symmetry are still apart.
+fn solve_all() -> HashMap<u16, (Value, bool)> {
+    let mut positions = HashMap::new();
+    solve(&mut Board::default(), Player::ONE, &mut positions);
+    positions
+}
+
+/// Stores the value of the position and whether the game is over.
+fn solve(board: &mut Board, player: Player, positions: &mut HashMap<u16, (Value, bool)>) -> Value {
+    let key = index(board, false).expect("Only the classic board gets solved");
+    if let Some(&(value, _)) = positions.get(&key) {
+        return value;
+    }
+    let (value, finished) = match board.get_board_state() {
+        BoardState::WON(winner) if winner == player => (Value::WIN(0), true),
+        BoardState::WON(_) => (Value::LOSS(0), true),
+        BoardState::ONGOING => {
+            let fields: Vec<(usize, usize)> = board.empty_cells().collect();
+            let best = fields
+                .into_iter()
+                .map(|field| {
+                    board
+                        .set_value(player, field)
+                        .expect("Empty cells can be set");
+                    let value = solve(board, player.other(), positions);
+                    board.clear_value(field).expect("The move was just played");
+                    one_move_earlier(value)
+                })
+                .max()
+                .expect("An ongoing game has empty cells");
+            (best, false)
+        }
+        _ => (Value::DRAW, true),
+    };
+    positions.insert(key, (value, finished));
+    value
+}
+
+/// The table as it is stored in `tablebase.bin`.
+pub fn generate() -> Vec<u8> {
+    let entries: BTreeMap<u16, Value> = solve_all()
+        .into_iter()
+        .map(|(key, (value, _))| (canonical(key), value))
+        .collect();
+    entries
+        .into_iter()
+        .flat_map(|(key, value)| {
+            let [low, high] = key.to_le_bytes();
+            [low, high, encode(value)]
+        })
+        .collect()
+}
+
+/// How many positions the game has, to compare with the known numbers.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Counts {
+    pub positions: usize,
+    pub unique: usize,
+    pub finished: usize,
+    pub unique_finished: usize,
+    /// Unique positions the player to move wins, draws and loses.
+    pub outcomes: [usize; 3],
+}
+
+impl Counts {
+    pub fn lines(&self) -> Vec<String> {
+        vec![
+            format!(
+                "Reachable positions: {} ({} up to symmetry)",
+                self.positions, self.unique
+            ),
+            format!(
+                "Finished games:      {} ({} up to symmetry)",
+                self.finished, self.unique_finished
+            ),
+            format!(
+                "Up to symmetry the player to move wins {}, draws {} and loses {}",
+                self.outcomes[0], self.outcomes[1], self.outcomes[2]
+            ),
+            format!(
+                "The stored table has {} entries in {} bytes",
+                TABLE.len() / ENTRY,
+                TABLE.len()
+            ),
+        ]
+    }
+}
+
+pub fn counts() -> Counts {
+    let positions = solve_all();
+    let mut unique = HashMap::new();
+    let mut counts = Counts {
+        positions: positions.len(),
+        ..Counts::default()
+    };
+    for (key, (value, finished)) in positions {
+        counts.finished += finished as usize;
+        unique.insert(canonical(key), (value, finished));
+    }
+    counts.unique = unique.len();
+    for (value, finished) in unique.values() {
+        counts.unique_finished += *finished as usize;
+        let outcome = match value {
+            Value::WIN(_) => 0,
+            Value::DRAW => 1,
+            Value::LOSS(_) => 2,
+        };
+        counts.outcomes[outcome] += 1;
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn stored_table_is_up_to_date() {
+        assert!(
+            generate() == TABLE,
+            "Run `cargo run -- tablebase --output src/game/tablebase.bin`"
+        );
+    }
+
+    #[test]
+    fn counts_match_the_known_numbers() {
+        let counts = counts();
+        assert_eq!(5478, counts.positions);
+        assert_eq!(765, counts.unique);
+        assert_eq!(958, counts.finished);
+        assert_eq!(138, counts.unique_finished);
+        assert_eq!(765 * ENTRY, TABLE.len());
+    }
+
+    #[test]
+    fn symmetric_positions_share_a_key() {
+        let corners = [(0, 0), (0, 2), (2, 0), (2, 2)];
+        let keys: Vec<u16> = corners
+            .iter()
+            .map(|&corner| {
+                let mut board = Board::default();
+                let _ = board.set_value(Player::ONE, corner);
+                canonical(index(&board, false).unwrap())
+            })
+            .collect();
+        assert!(keys.iter().all(|&key| key == keys[0]));
+    }
+
+    #[test]
+    fn values_survive_encoding() {
+        for value in [Value::DRAW, Value::WIN(1), Value::WIN(9), Value::LOSS(0)] {
+            assert_eq!(value, decode(encode(value)));
+        }
+    }
+
+    #[test]
+    fn lookups_find_the_value() {
+        let mut board = Board::default();
+        assert_eq!(Some(Value::DRAW), lookup(&board, Player::ONE));
+        let _ = board.set_value(Player::ONE, (0, 0));
+        let _ = board.set_value(Player::TWO, (0, 1));
+        assert_eq!(Some(Value::WIN(5)), lookup(&board, Player::ONE));
+        let values = move_values(&board, Player::ONE).unwrap();
+        assert_eq!(7, values.len());
+        assert!(values.contains(&((1, 1), Value::WIN(5))));
+
+        // The same position in a game that Player TWO started
+        let mut swapped = Board::default();
+        let _ = swapped.set_value(Player::TWO, (0, 0));
+        let _ = swapped.set_value(Player::ONE, (0, 1));
+        assert_eq!(Some(Value::WIN(5)), lookup(&swapped, Player::TWO));
+    }
+
+    #[test]
+    fn unreachable_positions_are_not_in_the_table() {
+        let mut board = Board::default();
+        let _ = board.set_value(Player::ONE, (0, 0));
+        let _ = board.set_value(Player::ONE, (0, 1));
+        assert_eq!(None, lookup(&board, Player::TWO));
+        assert_eq!(None, lookup(&board, Player::ONE));
+    }
+}
@@ -0,0 +1,344 @@
+use crate::game::board::GameResult;
+use crate::game::engine::{self, Engine, Position, Strategy};
+use crate::game::player::Player;
+use crate::game::stats::Record;
+
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// Who plays whom.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Format {
+    /// Everybody against everybody.
+    ROUNDROBIN,
+    /// The first engine against each of the others.
+    GAUNTLET,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "round-robin" => Ok(Self::ROUNDROBIN),
+            "gauntlet" => Ok(Self::GAUNTLET),
+            _ => Err(format!(
+                "Unknown format '{}'. Use round-robin or gauntlet",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct Tally {
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+}
+
+impl Tally {
+    fn add(&mut self, score: Score) {
+        match score {
+            Score::WIN => self.wins += 1,
+            Score::DRAW => self.draws += 1,
+            Score::LOSS => self.losses += 1,
+        }
+    }
+
+    fn merge(&mut self, other: Tally) {
+        self.wins += other.wins;
+        self.draws += other.draws;
+        self.losses += other.losses;
+    }
+
+    pub fn games(&self) -> usize {
+        self.wins + self.draws + self.losses
+    }
+
+    /// The average score per game and the half width of its 95% confidence interval.
+    pub fn score(&self) -> (f64, f64) {
+        let games = self.games() as f64;
+        if games == 0.0 {
+            return (0.0, 0.0);
+        }
+        let mean = (self.wins as f64 + self.draws as f64 / 2.0) / games;
+        let squares = self.wins as f64 + self.draws as f64 / 4.0;
+        let variance = squares / games - mean * mean;
+        (mean, 1.96 * (variance / games).sqrt())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Score {
+    WIN,
+    DRAW,
+    LOSS,
+}
+
+#[derive(Debug)]
+pub struct Tournament {
+    engines: Vec<Strategy>,
+    format: Format,
+    games: usize,
+    threads: usize,
+    seed: Option<u64>,
+}
+
+impl Tournament {
+    pub fn new(engines: Vec<Strategy>) -> Self {
+        Tournament {
+            engines,
+            format: Format::ROUNDROBIN,
+            games: 10,
+            threads: thread::available_parallelism().map_or(1, |threads| threads.get()),
+            seed: None,
+        }
+    }
+
+    pub fn with_format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// How often each pairing plays. The engines take turns at moving first.
+    pub fn with_games(mut self, games: usize) -> Self {
+        self.games = games;
+        self
+    }
+
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Makes every game reproducible, no matter how many threads play them.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    fn pairings(&self) -> Vec<(usize, usize)> {
+        let count = self.engines.len();
+        match self.format {
+            Format::ROUNDROBIN => (0..count)
+                .flat_map(|a| (a + 1..count).map(move |b| (a, b)))
+                .collect(),
+            Format::GAUNTLET => (1..count).map(|b| (0, b)).collect(),
+        }
+    }
+
+    /// Plays all games starting from `start`, spread over the threads.
+    /// Threads left over when there are fewer games than threads help the engines search.
+    pub fn run<P: Position>(&self, start: &P) -> Crosstable {
+        let jobs: Vec<(usize, usize, usize)> = self
+            .pairings()
+            .into_iter()
+            .flat_map(|(a, b)| (0..self.games).map(move |game| (a, b, game)))
+            .collect();
+        let next_job = AtomicUsize::new(0);
+        let played = Mutex::new(Vec::with_capacity(jobs.len()));
+        let workers = self.threads.min(jobs.len()).max(1);
+        let search_threads = self.threads / workers;
+
+        thread::scope(|scope| {
+            for _ in 0..workers {
+                scope.spawn(|| loop {
+                    let idx = next_job.fetch_add(1, Ordering::Relaxed);
+                    let (a, b, game) = match jobs.get(idx) {
+                        Some(&job) => job,
+                        None => break,
+                    };
+                    // Odd games swap who moves first
+                    let (one, two) = if game % 2 == 0 { (a, b) } else { (b, a) };
+                    let mut engines = [
+                        Engine::new(self.engines[one]).with_threads(search_threads),
+                        Engine::new(self.engines[two]).with_threads(search_threads),
+                    ];
+                    if let Some(seed) = self.seed {
+                        let seed = seed.wrapping_add(2 * idx as u64);
+                        engines[0] = engines[0].clone().with_seed(seed);
+                        engines[1] = engines[1].clone().with_seed(seed.wrapping_add(1));
+                    }
+                    let (state, moves) = engine::play_out(start, &mut engines);
+                    if let Some(result) = state.result() {
+                        played
+                            .lock()
+                            .expect("No thread panics while holding the lock")
+                            .push((idx, one, two, result, moves));
+                    }
+                });
+            }
+        });
+
+        let mut played = played.into_inner().expect("All threads are done");
+        played.sort_by_key(|(idx, ..)| *idx);
+        let mut table = Crosstable::new(&self.engines, self.games);
+        for (_, one, two, result, moves) in played {
+            table.add(one, two, result, moves);
+        }
+        table
+    }
+}
+
+/// The results of a tournament, seen from every engine against every other one.
+#[derive(Debug)]
+pub struct Crosstable {
+    names: Vec<String>,
+    games: usize,
+    tallies: Vec<Vec<Tally>>,
+    records: Vec<Record>,
+}
+
+impl Crosstable {
+    fn new(engines: &[Strategy], games: usize) -> Self {
+        Crosstable {
+            names: engines.iter().map(Strategy::to_string).collect(),
+            games,
+            tallies: vec![vec![Tally::default(); engines.len()]; engines.len()],
+            records: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, one: usize, two: usize, result: GameResult, moves: usize) {
+        let (score_one, score_two) = match result.winner {
+            Some(Player::ONE) => (Score::WIN, Score::LOSS),
+            Some(Player::TWO) => (Score::LOSS, Score::WIN),
+            None => (Score::DRAW, Score::DRAW),
+        };
+        self.tallies[one][two].add(score_one);
+        self.tallies[two][one].add(score_two);
+        self.records.push(Record::new(
+            [self.names[one].clone(), self.names[two].clone()],
+            result,
+            moves,
+        ));
+    }
+
+    pub fn tally(&self, engine: usize, opponent: usize) -> Tally {
+        self.tallies[engine][opponent]
+    }
+
+    pub fn total(&self, engine: usize) -> Tally {
+        let mut total = Tally::default();
+        for tally in &self.tallies[engine] {
+            total.merge(*tally);
+        }
+        total
+    }
+
+    /// Every game as it would be recorded for the statistics.
+    pub fn records(&self) -> &[Record] {
+        &self.records
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        let width = self.names.iter().map(String::len).max().unwrap_or(0).max(8);
+        let mut header = format!("{:<width$}", "", width = width);
+        for name in &self.names {
+            header.push_str(&format!(" {:>width$}", name, width = width));
+        }
+        header.push_str(&format!(" {:>7} {:>9}", "Score", "95% CI"));
+
+        let mut lines = vec![
+            format!("Crosstable (W-D-L, {} games per pairing)", self.games),
+            String::new(),
+            header,
+        ];
+        for (engine, name) in self.names.iter().enumerate() {
+            let mut line = format!("{:<width$}", name, width = width);
+            for opponent in 0..self.names.len() {
+                let tally = self.tally(engine, opponent);
+                let cell = if engine == opponent || tally.games() == 0 {
+                    "-".to_string()
+                } else {
+                    format!("{}-{}-{}", tally.wins, tally.draws, tally.losses)
+                };
+                line.push_str(&format!(" {:>width$}", cell, width = width));
+            }
+            let (mean, margin) = self.total(engine).score();
+            line.push_str(&format!(" {:>6.1}% {:>7.1}%", 100.0 * mean, 100.0 * margin));
+            lines.push(line);
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::game::engine::Turn;
+
+    #[test]
+    fn can_parse_formats() {
+        assert_eq!(Ok(Format::GAUNTLET), "gauntlet".parse());
+        assert_eq!(Ok(Format::ROUNDROBIN), "round-robin".parse());
+        assert!("swiss".parse::<Format>().is_err());
+    }
+
+    #[test]
+    fn formats_pair_engines() {
+        let engines = vec![Strategy::RANDOM, Strategy::GREEDY, Strategy::MINIMAX(2)];
+        let round_robin = Tournament::new(engines.clone());
+        assert_eq!(vec![(0, 1), (0, 2), (1, 2)], round_robin.pairings());
+        let gauntlet = Tournament::new(engines).with_format(Format::GAUNTLET);
+        assert_eq!(vec![(0, 1), (0, 2)], gauntlet.pairings());
+    }
+
+    #[test]
+    fn score_comes_with_a_confidence_interval() {
+        let all_draws = Tally {
+            wins: 0,
+            draws: 10,
+            losses: 0,
+        };
+        assert_eq!((0.5, 0.0), all_draws.score());
+        let mixed = Tally {
+            wins: 5,
+            draws: 0,
+            losses: 5,
+        };
+        let (mean, margin) = mixed.score();
+        assert_eq!(0.5, mean);
+        assert!((margin - 1.96 * 0.5 / 10f64.sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn perfect_play_never_loses() {
+        let table = Tournament::new(vec![Strategy::MINIMAX(9), Strategy::RANDOM])
+            .with_games(6)
+            .with_threads(3)
+            .with_seed(11)
+            .run(&Turn::default());
+        let tally = table.tally(0, 1);
+        assert_eq!((6, 0), (tally.games(), tally.losses));
+        assert_eq!(6, table.records().len());
+        assert_eq!(table.tally(1, 0).wins, tally.losses);
+    }
+
+    #[test]
+    fn seeded_tournaments_do_not_depend_on_threads() {
+        let engines = vec![Strategy::RANDOM, Strategy::GREEDY];
+        let run = |threads| {
+            Tournament::new(engines.clone())
+                .with_games(8)
+                .with_threads(threads)
+                .with_seed(3)
+                .run(&Turn::default())
+                .tally(0, 1)
+        };
+        assert_eq!(run(1), run(4));
+    }
+
+    #[test]
+    fn any_seed_can_be_used() {
+        let table = Tournament::new(vec![Strategy::RANDOM, Strategy::GREEDY])
+            .with_games(4)
+            .with_seed(u64::MAX)
+            .run(&Turn::default());
+        assert_eq!(4, table.total(0).games());
+    }
+}
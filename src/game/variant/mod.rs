@@ -0,0 +1,227 @@
+pub mod morris;
+pub mod notakto;
+pub mod order;
+pub mod quantum;
+pub mod qubic;
+pub mod ultimate;
+
+use crate::game::board::{GameResult, Reason};
+use crate::game::engine::Position;
+use crate::game::tournament::{Crosstable, Tournament};
+use crate::game::{get_input, is_quit, print_lines};
+
+use std::fmt;
+use std::str::FromStr;
+
+/// The games besides the classic one. Each is a `Position`, so the engines play all of them.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Variant {
+    /// A 3x3 board of 3x3 boards, the cell you play decides where your opponent plays.
+    ULTIMATE,
+    /// Both players set X on this many 3x3 boards, whoever completes the last line loses.
+    NOTAKTO(usize),
+    /// Three Men's Morris: three pieces each, placed and then moved, along the lines if `adjacent`.
+    MORRIS { adjacent: bool },
+    /// Quantum Tic-Tac-Toe: every mark is in two cells until a cycle makes it collapse.
+    QUANTUM,
+    /// Qubic: four in a row on a 4x4x4 cube.
+    QUBIC,
+    /// Order and Chaos: either symbol for either player, Order wants five in a row.
+    ORDER,
+}
+
+/// Written the way `--variant` takes it, so it also names the variant in the statistics.
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ULTIMATE => write!(f, "ultimate"),
+            Self::NOTAKTO(boards) => write!(f, "notakto:{}", boards),
+            Self::MORRIS { adjacent: true } => write!(f, "morris:adjacent"),
+            Self::MORRIS { adjacent: false } => write!(f, "morris:anywhere"),
+            Self::QUANTUM => write!(f, "quantum"),
+            Self::QUBIC => write!(f, "qubic"),
+            Self::ORDER => write!(f, "order"),
+        }
+    }
+}
+
+impl FromStr for Variant {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "ultimate" => Ok(Self::ULTIMATE),
+            None if s == "notakto" => Ok(Self::NOTAKTO(3)),
+            None if s == "quantum" => Ok(Self::QUANTUM),
+            None if s == "qubic" => Ok(Self::QUBIC),
+            None if s == "order" => Ok(Self::ORDER),
+            None if s == "morris" => Ok(Self::MORRIS { adjacent: true }),
+            Some(("morris", "adjacent")) => Ok(Self::MORRIS { adjacent: true }),
+            Some(("morris", "anywhere")) => Ok(Self::MORRIS { adjacent: false }),
+            Some(("notakto", boards)) => match boards.parse::<usize>() {
+                Ok(boards) if boards > 0 => Ok(Self::NOTAKTO(boards)),
+                _ => Err(format!("'{}' is not a positive number of boards", boards)),
+            },
+            _ => Err(format!(
+                "Unknown variant '{}'. Use ultimate, notakto[:<boards>], morris[:adjacent|anywhere], \
+                 quantum, qubic or order",
+                s
+            )),
+        }
+    }
+}
+
+/// The legend of variants that mark the cells a move can go to.
+const PLAYABLE_CELLS: &str = "Cells marked with · can be played.";
+
+/// What a variant needs on top of `Position` to be played at the terminal.
+pub trait Playable: Position {
+    fn name(&self) -> &'static str;
+
+    /// The position as text, from the column names down to a legend.
+    fn lines(&self) -> Vec<String>;
+
+    fn draw(&self) {
+        for line in self.lines() {
+            println!("{}", line);
+        }
+    }
+
+    /// Tells the player to move what to type.
+    fn prompt(&self) -> String;
+
+    fn parse_move(&self, input: &str) -> Result<Self::Move, String>;
+}
+
+/// Plays `variant` between two people at the terminal.
+pub fn play(variant: Variant) {
+    match variant {
+        Variant::ULTIMATE => play_out(ultimate::Ultimate::new()),
+        Variant::NOTAKTO(boards) => play_out(notakto::Notakto::new(boards)),
+        Variant::MORRIS { adjacent } => play_out(morris::Morris::new(adjacent)),
+        Variant::QUANTUM => play_out(quantum::Quantum::new()),
+        Variant::QUBIC => play_out(qubic::Qubic::new()),
+        Variant::ORDER => play_out(order::OrderChaos::new()),
+    }
+}
+
+/// Plays a tournament of `variant` between the engines.
+pub fn run_tournament(variant: Variant, tournament: &Tournament) -> Crosstable {
+    match variant {
+        Variant::ULTIMATE => tournament.run(&ultimate::Ultimate::new()),
+        Variant::NOTAKTO(boards) => tournament.run(&notakto::Notakto::new(boards)),
+        Variant::MORRIS { adjacent } => tournament.run(&morris::Morris::new(adjacent)),
+        Variant::QUANTUM => tournament.run(&quantum::Quantum::new()),
+        Variant::QUBIC => tournament.run(&qubic::Qubic::new()),
+        Variant::ORDER => tournament.run(&order::OrderChaos::new()),
+    }
+}
+
+fn play_out<P: Playable>(mut position: P) {
+    print_lines([
+        format!("Welcome to {}.", position.name()),
+        "Type a move, resign to give up or q to quit.".to_string(),
+    ]);
+    let result = loop {
+        if let Some(result) = position.state().result() {
+            position.draw();
+            break result;
+        }
+        let player = position.to_move();
+        print_lines([format!("It is player {:?}'s' turn", player)]);
+        position.draw();
+        print_lines([position.prompt()]);
+
+        let input = get_input(None).unwrap_or_default();
+        if is_quit(&input) {
+            break GameResult {
+                winner: None,
+                reason: Reason::ABANDONMENT,
+            };
+        }
+        if input == "resign" {
+            break GameResult {
+                winner: Some(player.other()),
+                reason: Reason::RESIGNATION,
+            };
+        }
+        match position.parse_move(&input) {
+            Ok(mv) if position.legal_moves().contains(&mv) => position.play(mv),
+            Ok(_) => print_lines(["That move is not allowed right now."]),
+            Err(e) => print_lines([e]),
+        }
+    };
+    print_lines([format!("Result: {}", result)]);
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::game::board::BoardState;
+    use crate::game::engine::{Engine, Strategy};
+    use crate::game::player::Player;
+
+    /// Every variant, with each option that changes how it is played.
+    const VARIANTS: [Variant; 8] = [
+        Variant::ULTIMATE,
+        Variant::NOTAKTO(1),
+        Variant::NOTAKTO(3),
+        Variant::MORRIS { adjacent: true },
+        Variant::MORRIS { adjacent: false },
+        Variant::QUANTUM,
+        Variant::QUBIC,
+        Variant::ORDER,
+    ];
+
+    /// Lets a search and a random engine play `start` to the end, checking every position on
+    /// the way: the players take turns, the engines only play legal moves, and a game is over
+    /// exactly when there is no move left.
+    fn check_play_out<P: Playable>(start: P) {
+        for (seed, searcher) in [(1, Player::ONE), (2, Player::TWO)] {
+            let mut engines = [
+                Engine::new(Strategy::MINIMAX(1)).with_seed(seed),
+                Engine::new(Strategy::RANDOM).with_seed(seed),
+            ];
+            if searcher == Player::TWO {
+                engines.swap(0, 1);
+            }
+            let mut position = start.clone();
+            let mut player = Player::ONE;
+            while position.state() == BoardState::ONGOING {
+                assert_eq!(player, position.to_move());
+                assert!(!position.lines().is_empty());
+                assert!(!position.prompt().is_empty());
+                let mv = engines[player.index()]
+                    .choose(&position)
+                    .expect("An ongoing game has a move");
+                assert!(position.legal_moves().contains(&mv), "{:?}", mv);
+                position.play(mv);
+                player = player.other();
+            }
+            assert!(position.legal_moves().is_empty());
+            assert!(position.state().result().is_some());
+        }
+    }
+
+    #[test]
+    fn variants_are_written_the_way_they_are_parsed() {
+        for variant in VARIANTS {
+            assert_eq!(Ok(variant), variant.to_string().parse());
+        }
+    }
+
+    #[test]
+    fn engines_play_every_variant_to_the_end() {
+        for variant in VARIANTS {
+            match variant {
+                Variant::ULTIMATE => check_play_out(ultimate::Ultimate::new()),
+                Variant::NOTAKTO(boards) => check_play_out(notakto::Notakto::new(boards)),
+                Variant::MORRIS { adjacent } => check_play_out(morris::Morris::new(adjacent)),
+                Variant::QUANTUM => check_play_out(quantum::Quantum::new()),
+                Variant::QUBIC => check_play_out(qubic::Qubic::new()),
+                Variant::ORDER => check_play_out(order::OrderChaos::new()),
+            }
+        }
+    }
+}
@@ -0,0 +1,344 @@
+use crate::game::board::{Board, BoardState};
+use crate::game::engine::{lines_heuristic, Position};
+use crate::game::notation::{cell_name, parse_cell};
+use crate::game::player::Player;
+use crate::game::variant::Playable;
+
+const SIZE: usize = 3;
+/// The pieces each player has.
+const PIECES: usize = 3;
+/// The game is drawn once this many moves were played, placing ones included.
+const MOVE_LIMIT: usize = 60;
+/// The game is drawn once a position comes up this many times with the same player to move.
+const REPETITIONS: usize = 3;
+
+/// A piece is placed until each player has all of theirs on the board, then pieces are moved.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Move {
+    PLACE((usize, usize)),
+    SLIDE {
+        from: (usize, usize),
+        to: (usize, usize),
+    },
+}
+
+/// Three Men's Morris. Each player places three pieces, then moves one of them to an empty
+/// cell every turn. Three in a row win.
+#[derive(Debug, Clone)]
+pub struct Morris {
+    board: Board,
+    player: Player,
+    /// Pieces may only move to a neighbouring cell along the lines of the board.
+    adjacent: bool,
+    /// The positions after every move so far, to tell when one repeats.
+    history: Vec<u64>,
+}
+
+impl Morris {
+    pub fn new(adjacent: bool) -> Self {
+        Morris {
+            board: Board::default(),
+            player: Player::ONE,
+            adjacent,
+            history: Vec::new(),
+        }
+    }
+
+    /// Whether pieces are still being placed.
+    fn is_placing(&self) -> bool {
+        self.board.empty_count() > SIZE * SIZE - 2 * PIECES
+    }
+
+    fn can_reach(&self, from: (usize, usize), to: (usize, usize)) -> bool {
+        if !self.adjacent {
+            return true;
+        }
+        let centre = (SIZE / 2, SIZE / 2);
+        let (rows, cols) = (from.0.abs_diff(to.0), from.1.abs_diff(to.1));
+        // Diagonal lines only run through the centre
+        rows <= 1 && cols <= 1 && (rows == 0 || cols == 0 || from == centre || to == centre)
+    }
+
+    /// Drawn by the move limit or by repetition.
+    fn is_drawn(&self) -> bool {
+        let key = self.position_key();
+        self.history.len() >= MOVE_LIMIT
+            || self.history.iter().filter(|&&seen| seen == key).count() >= REPETITIONS
+    }
+
+    fn position_key(&self) -> u64 {
+        self.board.hash() ^ (!0 * self.player.index() as u64)
+    }
+}
+
+impl Position for Morris {
+    type Move = Move;
+
+    fn to_move(&self) -> Player {
+        self.player
+    }
+
+    fn legal_moves(&self) -> Vec<Move> {
+        if self.board.get_board_state() != BoardState::ONGOING || self.is_drawn() {
+            return Vec::new();
+        }
+        if self.is_placing() {
+            return self.board.empty_cells().map(Move::PLACE).collect();
+        }
+        let pieces: Vec<(usize, usize)> = (0..SIZE * SIZE)
+            .map(|idx| (idx / SIZE, idx % SIZE))
+            .filter(|&(row, col)| self.board[row][col] == Some(self.player))
+            .collect();
+        pieces
+            .into_iter()
+            .flat_map(|from| {
+                self.board
+                    .empty_cells()
+                    .filter(move |&to| self.can_reach(from, to))
+                    .map(move |to| Move::SLIDE { from, to })
+            })
+            .collect()
+    }
+
+    fn play(&mut self, mv: Move) {
+        let to = match mv {
+            Move::PLACE(to) => to,
+            Move::SLIDE { from, to } => {
+                self.board
+                    .clear_value(from)
+                    .expect("Engines only move pieces that are there");
+                to
+            }
+        };
+        self.board
+            .set_value(self.player, to)
+            .expect("Engines only play legal moves");
+        self.player = self.player.other();
+        self.history.push(self.position_key());
+    }
+
+    /// A player who can't move any piece loses.
+    fn state(&self) -> BoardState {
+        match self.board.get_board_state() {
+            BoardState::ONGOING if self.is_drawn() => BoardState::DRAW,
+            BoardState::ONGOING if self.legal_moves().is_empty() => {
+                BoardState::WON(self.player.other())
+            }
+            state => state,
+        }
+    }
+
+    fn heuristic(&self, player: Player) -> i32 {
+        lines_heuristic(&self.board, player)
+    }
+}
+
+impl Playable for Morris {
+    fn name(&self) -> &'static str {
+        "Three Men's Morris"
+    }
+
+    fn lines(&self) -> Vec<String> {
+        let mut lines = vec![format!(
+            "  {}",
+            (0..SIZE)
+                .map(|col| format!("  {} ", (b'a' + col as u8) as char))
+                .collect::<Vec<String>>()
+                .join(" ")
+        )];
+        for row in 0..SIZE {
+            if row > 0 {
+                lines.push(format!("  {}", ["----"; SIZE].join("+")));
+            }
+            let cells: Vec<String> = self.board[row]
+                .iter()
+                .map(|cell| match cell {
+                    Some(player) => format!(" {} ", player),
+                    None => "    ".to_string(),
+                })
+                .collect();
+            lines.push(format!("{} {}", row + 1, cells.join("|")));
+        }
+        lines
+    }
+
+    fn prompt(&self) -> String {
+        if self.is_placing() {
+            let placed = (SIZE * SIZE - self.board.empty_count()) / 2;
+            format!(
+                "Place piece {} of {}: enter a cell like b2",
+                placed + 1,
+                PIECES
+            )
+        } else if self.adjacent {
+            "Move a piece along a line to the next cell: enter both cells, like a1 b2".to_string()
+        } else {
+            "Move a piece to any empty cell: enter both cells, like a1 c2".to_string()
+        }
+    }
+
+    /// `b2` places a piece, `a1 b2` moves the piece on a1 to b2.
+    fn parse_move(&self, input: &str) -> Result<Move, String> {
+        let cells = input
+            .split_whitespace()
+            .map(|name| match parse_cell(name)? {
+                (row, col) if row < SIZE && col < SIZE => Ok((row, col)),
+                cell => Err(format!(
+                    "'{}' is off the board, use a1 to c3",
+                    cell_name(cell)
+                )),
+            })
+            .collect::<Result<Vec<(usize, usize)>, String>>()?;
+        match (cells.as_slice(), self.is_placing()) {
+            ([to], true) => Ok(Move::PLACE(*to)),
+            ([from, to], false) => Ok(Move::SLIDE {
+                from: *from,
+                to: *to,
+            }),
+            (_, true) => Err("Place a piece first, enter a cell like b2".to_string()),
+            (_, false) => Err("All pieces are placed, enter two cells like a1 b2".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn play(game: &mut Morris, moves: &[Move]) {
+        for &mv in moves {
+            assert!(game.legal_moves().contains(&mv), "{:?}", mv);
+            game.play(mv);
+        }
+    }
+
+    /// Places the pieces so that nobody has a line:
+    /// ONE on a1, c1 and b3, TWO on b1, a2 and c2.
+    fn placed(adjacent: bool) -> Morris {
+        let mut game = Morris::new(adjacent);
+        let cells = [(0, 0), (0, 1), (0, 2), (1, 0), (2, 1), (1, 2)];
+        play(&mut game, &cells.map(Move::PLACE));
+        game
+    }
+
+    #[test]
+    fn pieces_move_once_all_are_placed() {
+        let game = placed(false);
+        assert!(!game.is_placing());
+        assert_eq!(Player::ONE, game.to_move());
+        // Three pieces, three empty cells
+        assert_eq!(9, game.legal_moves().len());
+        assert!(game
+            .legal_moves()
+            .iter()
+            .all(|mv| matches!(mv, Move::SLIDE { .. })));
+    }
+
+    #[test]
+    fn adjacent_moves_follow_the_lines() {
+        let game = placed(true);
+        let mut moves = game.legal_moves();
+        moves.sort_by_key(|mv| format!("{:?}", mv));
+        // a1 reaches the centre along the diagonal, c1 too, b3 reaches a3, b2 and c3
+        assert_eq!(
+            vec![
+                Move::SLIDE {
+                    from: (0, 0),
+                    to: (1, 1)
+                },
+                Move::SLIDE {
+                    from: (0, 2),
+                    to: (1, 1)
+                },
+                Move::SLIDE {
+                    from: (2, 1),
+                    to: (1, 1)
+                },
+                Move::SLIDE {
+                    from: (2, 1),
+                    to: (2, 0)
+                },
+                Move::SLIDE {
+                    from: (2, 1),
+                    to: (2, 2)
+                },
+            ],
+            moves
+        );
+    }
+
+    #[test]
+    fn moving_into_a_line_wins() {
+        let mut game = placed(false);
+        play(
+            &mut game,
+            &[
+                Move::SLIDE {
+                    from: (0, 0),
+                    to: (1, 1),
+                },
+                Move::SLIDE {
+                    from: (0, 1),
+                    to: (2, 0),
+                },
+                Move::SLIDE {
+                    from: (0, 2),
+                    to: (0, 1),
+                },
+            ],
+        );
+        // ONE on b1, b2 and b3
+        assert_eq!(BoardState::WON(Player::ONE), game.state());
+        assert!(game.legal_moves().is_empty());
+    }
+
+    #[test]
+    fn repeating_a_position_draws() {
+        let mut game = placed(false);
+        let there = [
+            Move::SLIDE {
+                from: (0, 0),
+                to: (2, 0),
+            },
+            Move::SLIDE {
+                from: (1, 0),
+                to: (1, 1),
+            },
+        ];
+        let back = [
+            Move::SLIDE {
+                from: (2, 0),
+                to: (0, 0),
+            },
+            Move::SLIDE {
+                from: (1, 1),
+                to: (1, 0),
+            },
+        ];
+        play(&mut game, &there);
+        play(&mut game, &back);
+        play(&mut game, &there);
+        assert_eq!(BoardState::ONGOING, game.state());
+        play(&mut game, &back);
+        assert_eq!(BoardState::DRAW, game.state());
+        assert!(game.legal_moves().is_empty());
+    }
+
+    #[test]
+    fn moves_can_be_typed() {
+        let mut game = Morris::new(true);
+        assert_eq!(Ok(Move::PLACE((1, 1))), game.parse_move("b2"));
+        assert!(game.parse_move("a1 b2").is_err());
+        assert!(game.parse_move("d1").is_err());
+        game = placed(true);
+        assert_eq!(
+            Ok(Move::SLIDE {
+                from: (0, 0),
+                to: (1, 1)
+            }),
+            game.parse_move("a1 b2")
+        );
+        assert!(game.parse_move("b2").is_err());
+    }
+}
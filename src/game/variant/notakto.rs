@@ -0,0 +1,278 @@
+use crate::game::board::{Board, BoardState};
+use crate::game::engine::Position;
+use crate::game::notation::{cell_name, parse_cell};
+use crate::game::player::Player;
+use crate::game::variant::{Playable, PLAYABLE_CELLS};
+
+const SIZE: usize = 3;
+
+/// Where an X goes: the number of the board, counted from 0, then the cell in it.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Move {
+    pub board: usize,
+    pub cell: (usize, usize),
+}
+
+/// Notakto. Both players set an X on any live 3x3 board, a board with three in a row is dead.
+/// Whoever kills the last live board loses.
+#[derive(Debug, Clone)]
+pub struct Notakto {
+    /// Every X is set as Player ONE, so a dead board is one that Player ONE won.
+    boards: Vec<Board>,
+    player: Player,
+}
+
+impl Notakto {
+    pub fn new(boards: usize) -> Self {
+        Notakto {
+            boards: vec![Board::default(); boards],
+            player: Player::ONE,
+        }
+    }
+
+    fn is_live(&self, idx: usize) -> bool {
+        self.boards[idx].get_board_state() == BoardState::ONGOING
+    }
+
+    fn live_boards(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.boards.len()).filter(move |&idx| self.is_live(idx))
+    }
+}
+
+impl Position for Notakto {
+    type Move = Move;
+
+    fn to_move(&self) -> Player {
+        self.player
+    }
+
+    fn legal_moves(&self) -> Vec<Move> {
+        self.live_boards()
+            .flat_map(|board| {
+                self.boards[board]
+                    .empty_cells()
+                    .map(move |cell| Move { board, cell })
+            })
+            .collect()
+    }
+
+    fn play(&mut self, mv: Move) {
+        self.boards[mv.board]
+            .set_value(Player::ONE, mv.cell)
+            .expect("Engines only play legal moves");
+        self.player = self.player.other();
+    }
+
+    /// The player who killed the last board has just moved, so the player to move wins.
+    fn state(&self) -> BoardState {
+        match self.live_boards().next() {
+            Some(_) => BoardState::ONGOING,
+            None => BoardState::WON(self.player),
+        }
+    }
+
+    /// Dead boards no longer matter and the live ones can be swapped, rotated and reflected.
+    /// Both players play the same moves, so the key leaves out who is to move.
+    fn key(&self) -> Option<u64> {
+        let mut keys: Vec<u64> = self
+            .live_boards()
+            .map(|idx| self.boards[idx].canonical_key())
+            .collect();
+        keys.sort_unstable();
+        Some(
+            keys.into_iter()
+                .fold(0, |key, board| key.rotate_left(13) ^ board),
+        )
+    }
+}
+
+impl Playable for Notakto {
+    fn name(&self) -> &'static str {
+        "Notakto"
+    }
+
+    fn lines(&self) -> Vec<String> {
+        let width = 4 * SIZE + SIZE - 1;
+        let labels: Vec<String> = (0..self.boards.len())
+            .map(|idx| {
+                let label = if self.is_live(idx) {
+                    format!("Board {}", idx + 1)
+                } else {
+                    format!("Board {} (dead)", idx + 1)
+                };
+                format!("{:^width$}", label, width = width)
+            })
+            .collect();
+        let columns: Vec<String> = (0..SIZE)
+            .map(|col| format!("  {} ", (b'a' + col as u8) as char))
+            .collect();
+        let mut lines = vec![
+            format!("  {}", labels.join("   ")),
+            format!(
+                "  {}",
+                vec![columns.join(" "); self.boards.len()].join("   ")
+            ),
+        ];
+        for row in 0..SIZE {
+            if row > 0 {
+                let separator = ["----"; SIZE].join("+");
+                lines.push(format!(
+                    "  {}",
+                    vec![separator; self.boards.len()].join("   ")
+                ));
+            }
+            let boards: Vec<String> = (0..self.boards.len())
+                .map(|idx| {
+                    let live = self.is_live(idx);
+                    self.boards[idx][row]
+                        .iter()
+                        .map(|cell| match cell {
+                            Some(_) => "  X ",
+                            None if live => "  · ",
+                            None => "    ",
+                        })
+                        .collect::<Vec<&str>>()
+                        .join("|")
+                })
+                .collect();
+            lines.push(format!("{} {}", row + 1, boards.join("   ")));
+        }
+        lines.push(String::new());
+        lines.push(PLAYABLE_CELLS.to_string());
+        lines
+    }
+
+    fn prompt(&self) -> String {
+        let live: Vec<usize> = self.live_boards().collect();
+        match live.as_slice() {
+            [idx] => format!(
+                "Only board {} is left: enter a cell like b2, or the board and the cell like {} b2",
+                idx + 1,
+                idx + 1
+            ),
+            _ => "Enter the board and the cell, like 1 b2. Whoever kills the last board loses"
+                .to_string(),
+        }
+    }
+
+    /// `2 b2` is cell b2 of the second board. The board can be left out when only one is live.
+    fn parse_move(&self, input: &str) -> Result<Move, String> {
+        let names: Vec<&str> = input.split_whitespace().collect();
+        let live: Vec<usize> = self.live_boards().collect();
+        let (board, cell) = match (names.as_slice(), live.as_slice()) {
+            ([board, cell], _) => {
+                let board = board
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&board| (1..=self.boards.len()).contains(&board))
+                    .ok_or_else(|| {
+                        format!("'{}' is not a board, use 1 to {}", board, self.boards.len())
+                    })?;
+                (board - 1, parse_cell(cell)?)
+            }
+            ([cell], [idx]) => (*idx, parse_cell(cell)?),
+            ([_], _) => return Err("Enter the board and the cell, like 1 b2".to_string()),
+            _ => return Err(format!("'{}' is not a move like 1 b2", input)),
+        };
+        let (row, col) = cell;
+        if row >= SIZE || col >= SIZE {
+            return Err(format!(
+                "'{}' is off the board, use a1 to c3",
+                cell_name(cell)
+            ));
+        }
+        Ok(Move { board, cell })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::game::engine::{play_out, Engine, Strategy};
+
+    fn play(game: &mut Notakto, moves: &[(usize, (usize, usize))]) {
+        for &(board, cell) in moves {
+            let mv = Move { board, cell };
+            assert!(game.legal_moves().contains(&mv), "{:?}", mv);
+            game.play(mv);
+        }
+    }
+
+    #[test]
+    fn killing_the_last_board_loses() {
+        let mut game = Notakto::new(2);
+        assert_eq!(18, game.legal_moves().len());
+        play(&mut game, &[(0, (0, 0)), (0, (0, 1)), (0, (0, 2))]);
+        // Board 1 is dead, only board 2 is left to play in
+        assert!(!game.is_live(0));
+        assert_eq!(9, game.legal_moves().len());
+        assert_eq!(BoardState::ONGOING, game.state());
+        play(&mut game, &[(1, (0, 0)), (1, (1, 1)), (1, (2, 2))]);
+        // Player TWO set the last X
+        assert_eq!(BoardState::WON(Player::ONE), game.state());
+        assert!(game.legal_moves().is_empty());
+    }
+
+    #[test]
+    fn first_player_wins_on_one_board_with_the_centre() {
+        let centre = Move {
+            board: 0,
+            cell: (1, 1),
+        };
+        for seed in 1..5 {
+            let mut engine = Engine::new(Strategy::MINIMAX(9)).with_seed(seed);
+            assert_eq!(Some(centre), engine.choose(&Notakto::new(1)));
+        }
+    }
+
+    #[test]
+    fn swapped_boards_share_a_key() {
+        let mut first = Notakto::new(2);
+        let mut second = Notakto::new(2);
+        play(&mut first, &[(0, (0, 0)), (1, (1, 1))]);
+        play(&mut second, &[(1, (2, 2)), (0, (1, 1))]);
+        assert_eq!(first.key(), second.key());
+        play(&mut first, &[(0, (0, 1)), (0, (0, 2))]);
+        play(&mut second, &[(1, (2, 1)), (1, (2, 0))]);
+        // Only the board with the centre is left in both
+        assert_eq!(first.key(), second.key());
+    }
+
+    #[test]
+    fn there_are_no_draws() {
+        for seed in 0..20 {
+            let mut engines = [
+                Engine::new(Strategy::RANDOM).with_seed(seed),
+                Engine::new(Strategy::RANDOM).with_seed(seed + 100),
+            ];
+            let (state, _) = play_out(&Notakto::new(3), &mut engines);
+            assert!(matches!(state, BoardState::WON(_)), "{:?}", state);
+        }
+    }
+
+    #[test]
+    fn moves_name_the_board_then_the_cell() {
+        let mut game = Notakto::new(2);
+        assert_eq!(
+            Ok(Move {
+                board: 1,
+                cell: (1, 1)
+            }),
+            game.parse_move("2 b2")
+        );
+        assert!(game.parse_move("b2").is_err());
+        assert!(game.parse_move("3 b2").is_err());
+        assert!(game.parse_move("1 d1").is_err());
+        play(&mut game, &[(1, (0, 0)), (1, (1, 1)), (1, (2, 2))]);
+        assert_eq!(
+            Ok(Move {
+                board: 0,
+                cell: (0, 2)
+            }),
+            game.parse_move("c1")
+        );
+        let lines = game.lines();
+        assert!(lines[0].contains("Board 2 (dead)"));
+    }
+}
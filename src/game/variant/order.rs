@@ -0,0 +1,293 @@
+use crate::game::board::{Board, BoardState, Reason, Shape};
+use crate::game::engine::Position;
+use crate::game::notation::{cell_name, parse_cell};
+use crate::game::player::Player;
+use crate::game::variant::Playable;
+
+use std::fmt;
+
+const SIZE: usize = 6;
+const WIN_LENGTH: usize = 5;
+
+/// What a player is after. Order moves first and is Player ONE.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Role {
+    /// Wants five of the same symbol in a row.
+    ORDER,
+    /// Wants the board to fill up without that.
+    CHAOS,
+}
+
+impl Role {
+    pub fn of(player: Player) -> Self {
+        match player {
+            Player::ONE => Role::ORDER,
+            Player::TWO => Role::CHAOS,
+        }
+    }
+
+    pub fn player(self) -> Player {
+        match self {
+            Role::ORDER => Player::ONE,
+            Role::CHAOS => Player::TWO,
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Role::ORDER => write!(f, "Order"),
+            Role::CHAOS => write!(f, "Chaos"),
+        }
+    }
+}
+
+/// The cell and the symbol set on it. The symbols are the marks of the two players, X is
+/// Player ONE's mark and O Player TWO's, but either player may set either.
+pub type Move = ((usize, usize), Player);
+
+/// Order and Chaos on a 6x6 board. Both players set X or O as they like. Order wins with five
+/// of the same symbol in a row, whoever set them, and Chaos wins once the board is full.
+#[derive(Debug, Clone)]
+pub struct OrderChaos {
+    board: Board,
+    player: Player,
+}
+
+impl OrderChaos {
+    pub fn new() -> Self {
+        OrderChaos {
+            board: Board::new(Shape {
+                rows: SIZE,
+                cols: SIZE,
+                win_length: WIN_LENGTH,
+                layers: 1,
+            }),
+            player: Role::ORDER.player(),
+        }
+    }
+
+    /// How good the board looks for Order. Lines that hold only one symbol can still become
+    /// five in a row, and the more they hold the better. Lines with both symbols are lost.
+    fn order_score(&self) -> i32 {
+        let mut open = 0;
+        let mut score = 0;
+        for line in self.board.get_lines() {
+            let xs = line
+                .iter()
+                .filter(|&&cell| cell == Some(Player::ONE))
+                .count() as u32;
+            let os = line
+                .iter()
+                .filter(|&&cell| cell == Some(Player::TWO))
+                .count() as u32;
+            if xs > 0 && os > 0 {
+                continue;
+            }
+            open += 1;
+            score += 10i32.pow(xs.max(os));
+        }
+        // Without a line left to fill, Chaos only has to wait for the board to fill up
+        match open {
+            0 => -100_000,
+            _ => score,
+        }
+    }
+}
+
+impl Default for OrderChaos {
+    fn default() -> Self {
+        OrderChaos::new()
+    }
+}
+
+fn symbol(mark: Player) -> char {
+    match mark {
+        Player::ONE => 'X',
+        Player::TWO => 'O',
+    }
+}
+
+impl Position for OrderChaos {
+    type Move = Move;
+
+    fn to_move(&self) -> Player {
+        self.player
+    }
+
+    fn legal_moves(&self) -> Vec<Move> {
+        if self.state() != BoardState::ONGOING {
+            return Vec::new();
+        }
+        self.board
+            .empty_cells()
+            .flat_map(|field| [(field, Player::ONE), (field, Player::TWO)])
+            .collect()
+    }
+
+    fn play(&mut self, (field, mark): Move) {
+        self.board
+            .set_mark(self.player, mark, field)
+            .expect("Engines only play legal moves");
+        self.player = self.player.other();
+    }
+
+    /// A line goes to Order whoever completed it, a full board to Chaos.
+    fn state(&self) -> BoardState {
+        match self.board.get_board_state() {
+            BoardState::WON(_) => BoardState::WON(Role::ORDER.player()),
+            BoardState::DRAW => BoardState::ENDED(Some(Role::CHAOS.player()), Reason::FULL),
+            state => state,
+        }
+    }
+
+    /// Order looks for lines to fill, Chaos for lines to spoil.
+    fn heuristic(&self, player: Player) -> i32 {
+        match Role::of(player) {
+            Role::ORDER => self.order_score(),
+            Role::CHAOS => -self.order_score(),
+        }
+    }
+
+    fn key(&self) -> Option<u64> {
+        let side = !0 * self.player.index() as u64;
+        Some(self.board.canonical_key() ^ side)
+    }
+}
+
+impl Playable for OrderChaos {
+    fn name(&self) -> &'static str {
+        "Order and Chaos"
+    }
+
+    fn lines(&self) -> Vec<String> {
+        let mut lines = vec![format!(
+            "  {}",
+            (0..SIZE)
+                .map(|col| format!("  {} ", (b'a' + col as u8) as char))
+                .collect::<Vec<String>>()
+                .join(" ")
+        )];
+        for row in 0..SIZE {
+            if row > 0 {
+                lines.push(format!("  {}", ["----"; SIZE].join("+")));
+            }
+            let cells: Vec<String> = self.board[row]
+                .iter()
+                .map(|cell| match cell {
+                    Some(mark) => format!("  {} ", symbol(*mark)),
+                    None => "    ".to_string(),
+                })
+                .collect();
+            lines.push(format!("{} {}", row + 1, cells.join("|")));
+        }
+        lines.push(String::new());
+        lines.push(format!(
+            "Player ONE is {}: five X or five O in a row win. Player TWO is {}: a full board \
+             without them wins.",
+            Role::ORDER,
+            Role::CHAOS
+        ));
+        lines
+    }
+
+    fn prompt(&self) -> String {
+        format!(
+            "{} to move: enter a cell and a symbol, like c3 x or c3 o",
+            Role::of(self.player)
+        )
+    }
+
+    /// `c3 x` sets an X on c3. The symbol may also come first.
+    fn parse_move(&self, input: &str) -> Result<Move, String> {
+        let (cell, mark) = match input.split_whitespace().collect::<Vec<&str>>()[..] {
+            [cell, mark @ ("x" | "o")] | [mark @ ("x" | "o"), cell] => (cell, mark),
+            _ => return Err(format!("'{}' is not a cell and a symbol like c3 x", input)),
+        };
+        let field = parse_cell(cell)?;
+        if field.0 >= SIZE || field.1 >= SIZE {
+            return Err(format!(
+                "'{}' is off the board, use a1 to f6",
+                cell_name(field)
+            ));
+        }
+        let mark = match mark {
+            "x" => Player::ONE,
+            _ => Player::TWO,
+        };
+        Ok((field, mark))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::game::engine::{Engine, Strategy};
+
+    #[test]
+    fn any_line_goes_to_order() {
+        let mut game = OrderChaos::new();
+        assert_eq!(72, game.legal_moves().len());
+        game.play(((5, 5), Player::ONE));
+        for row in 0..4 {
+            game.play(((row, 2), Player::TWO));
+        }
+        assert_eq!(BoardState::ONGOING, game.state());
+        // Chaos sets the O that completes the column
+        assert_eq!(Role::CHAOS, Role::of(game.to_move()));
+        game.play(((4, 2), Player::TWO));
+        assert_eq!(BoardState::WON(Role::ORDER.player()), game.state());
+    }
+
+    #[test]
+    fn full_board_goes_to_chaos() {
+        let mut game = OrderChaos::new();
+        // Runs of two at most, in every direction
+        for row in 0..SIZE {
+            for col in 0..SIZE {
+                let mark = if (2 * row + col) % 4 < 2 {
+                    Player::ONE
+                } else {
+                    Player::TWO
+                };
+                assert_eq!(BoardState::ONGOING, game.state());
+                game.play(((row, col), mark));
+            }
+        }
+        let state = game.state();
+        assert_eq!(
+            BoardState::ENDED(Some(Role::CHAOS.player()), Reason::FULL),
+            state
+        );
+        assert_eq!(Some(Player::TWO), state.result().and_then(|r| r.winner));
+    }
+
+    #[test]
+    fn sides_see_the_board_differently() {
+        let mut game = OrderChaos::new();
+        for col in 0..4 {
+            game.play(((0, col), Player::ONE));
+        }
+        let order = game.heuristic(Role::ORDER.player());
+        assert!(order > 0);
+        assert_eq!(-order, game.heuristic(Role::CHAOS.player()));
+
+        // Order finishes the row, Chaos blocks it with an O
+        let mut engine = Engine::new(Strategy::MINIMAX(1)).with_seed(1);
+        assert_eq!(Some(((0, 4), Player::ONE)), engine.choose(&game));
+        game.player = Role::CHAOS.player();
+        let mut engine = Engine::new(Strategy::MINIMAX(2)).with_seed(1);
+        assert_eq!(Some(((0, 4), Player::TWO)), engine.choose(&game));
+    }
+
+    #[test]
+    fn moves_name_a_cell_and_a_symbol() {
+        let game = OrderChaos::new();
+        assert_eq!(Ok(((2, 2), Player::ONE)), game.parse_move("c3 x"));
+        assert_eq!(Ok(((5, 5), Player::TWO)), game.parse_move("o f6"));
+        assert!(game.parse_move("c3").is_err());
+        assert!(game.parse_move("g1 x").is_err());
+    }
+}
@@ -0,0 +1,555 @@
+use crate::game::board::{Board, BoardState};
+use crate::game::engine::Position;
+use crate::game::notation::{cell_name, parse_cell};
+use crate::game::player::Player;
+use crate::game::variant::Playable;
+
+use std::collections::VecDeque;
+
+const SIZE: usize = 3;
+
+type Cell = (usize, usize);
+
+/// The rows, columns and diagonals of the board.
+const LINES: [[Cell; SIZE]; 8] = [
+    [(0, 0), (0, 1), (0, 2)],
+    [(1, 0), (1, 1), (1, 2)],
+    [(2, 0), (2, 1), (2, 2)],
+    [(0, 0), (1, 0), (2, 0)],
+    [(0, 1), (1, 1), (2, 1)],
+    [(0, 2), (1, 2), (2, 2)],
+    [(0, 0), (1, 1), (2, 2)],
+    [(0, 2), (1, 1), (2, 0)],
+];
+
+/// A mark that is in two cells at once until it collapses into one of them.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+struct Spooky {
+    player: Player,
+    /// The number of the move that placed it, starting at 1.
+    turn: usize,
+    cells: [Cell; 2],
+}
+
+/// A turn of Quantum Tic-Tac-Toe. Both parts are in one move, so the players keep taking turns.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Move {
+    /// Where the mark that closed a cycle goes. The player who didn't close it chooses.
+    pub collapse: Option<Cell>,
+    /// The two cells of the new spooky mark, `None` if the collapse ended the game.
+    pub spooky: Option<(Cell, Cell)>,
+    /// The last free cell, which takes a classical mark since a spooky one doesn't fit.
+    pub classical: Option<Cell>,
+}
+
+/// Quantum Tic-Tac-Toe. Every move puts a spooky mark in two cells. The cells are the nodes of
+/// a graph and the spooky marks its edges. A mark that closes a cycle makes the whole connected
+/// part of the graph collapse into classical marks, and only classical marks make lines.
+#[derive(Debug, Clone)]
+pub struct Quantum {
+    /// The classical marks.
+    board: Board,
+    /// The move that placed each classical mark, to score lines that appear at the same time.
+    turns: [[Option<usize>; SIZE]; SIZE],
+    /// The marks that didn't collapse yet.
+    spooky: Vec<Spooky>,
+    /// The mark that closed a cycle, the player to move collapses it first.
+    cycle: Option<Spooky>,
+    player: Player,
+    turn: usize,
+}
+
+impl Quantum {
+    pub fn new() -> Self {
+        Quantum {
+            board: Board::default(),
+            turns: [[None; SIZE]; SIZE],
+            spooky: Vec::new(),
+            cycle: None,
+            player: Player::ONE,
+            turn: 1,
+        }
+    }
+
+    /// Cells without a classical mark, the only ones a spooky mark can go to.
+    fn free_cells(&self) -> Vec<Cell> {
+        self.board.empty_cells().collect()
+    }
+
+    /// Whether spooky marks already connect the two cells, a walk through the graph from `from`.
+    fn is_connected(&self, from: Cell, to: Cell) -> bool {
+        let mut seen = vec![from];
+        let mut queue = VecDeque::from([from]);
+        while let Some(cell) = queue.pop_front() {
+            if cell == to {
+                return true;
+            }
+            for mark in &self.spooky {
+                if let Some(next) = other_cell(mark, cell) {
+                    if !seen.contains(&next) {
+                        seen.push(next);
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    /// Puts the mark into `cell`. Every other spooky mark in that cell has to go to its
+    /// other cell then, and so on through the whole connected part of the graph.
+    fn collapse(&mut self, mark: Spooky, cell: Cell) {
+        let mut queue = VecDeque::from([(mark, cell)]);
+        while let Some((mark, cell)) = queue.pop_front() {
+            self.spooky.retain(|other| *other != mark);
+            self.board
+                .set_value(mark.player, cell)
+                .expect("A collapse only fills free cells");
+            self.turns[cell.0][cell.1] = Some(mark.turn);
+            let (forced, rest): (Vec<Spooky>, Vec<Spooky>) = self
+                .spooky
+                .iter()
+                .partition(|other| other.cells.contains(&cell));
+            self.spooky = rest;
+            for other in forced {
+                if let Some(next) = other_cell(&other, cell) {
+                    queue.push_back((other, next));
+                }
+            }
+        }
+    }
+
+    fn place_classical(&mut self, cell: Cell) {
+        self.board
+            .set_value(self.player, cell)
+            .expect("Only a free cell takes a classical mark");
+        self.turns[cell.0][cell.1] = Some(self.turn);
+        self.turn += 1;
+    }
+
+    fn place(&mut self, (first, second): (Cell, Cell)) {
+        let mark = Spooky {
+            player: self.player,
+            turn: self.turn,
+            cells: [first, second],
+        };
+        if self.is_connected(first, second) {
+            self.cycle = Some(mark);
+        }
+        self.spooky.push(mark);
+        self.turn += 1;
+    }
+
+    /// The points of both players in halves: a line scores 2 for its player. When both
+    /// players got lines in the same collapse, the one whose line was finished by an earlier
+    /// move scores 2 and the other 1.
+    fn points(&self) -> [u32; 2] {
+        let mut last_turns: [Option<usize>; 2] = [None, None];
+        for line in LINES.iter() {
+            let owners = line.map(|(row, col)| self.board[row][col]);
+            if let [Some(player), second, third] = owners {
+                if second != Some(player) || third != Some(player) {
+                    continue;
+                }
+                let last = line
+                    .iter()
+                    .filter_map(|&(row, col)| self.turns[row][col])
+                    .max()
+                    .unwrap_or(0);
+                let best = &mut last_turns[player.index()];
+                *best = Some(best.map_or(last, |best| best.min(last)));
+            }
+        }
+        match last_turns {
+            [Some(one), Some(two)] if one < two => [2, 1],
+            [Some(_), Some(_)] => [1, 2],
+            [Some(_), None] => [2, 0],
+            [None, Some(_)] => [0, 2],
+            [None, None] => [0, 0],
+        }
+    }
+}
+
+impl Default for Quantum {
+    fn default() -> Self {
+        Quantum::new()
+    }
+}
+
+/// The cell at the other end of the mark, if the mark is in `cell` at all.
+fn other_cell(mark: &Spooky, cell: Cell) -> Option<Cell> {
+    match mark.cells {
+        [first, second] if first == cell => Some(second),
+        [first, second] if second == cell => Some(first),
+        _ => None,
+    }
+}
+
+/// Spooky marks are written in small letters, like x3.
+fn spooky_name(mark: &Spooky) -> String {
+    format!("{}{}", letter(mark.player).to_ascii_lowercase(), mark.turn)
+}
+
+fn letter(player: Player) -> char {
+    match player {
+        Player::ONE => 'X',
+        Player::TWO => 'O',
+    }
+}
+
+fn half_points(halves: u32) -> String {
+    match halves {
+        1 => "½".to_string(),
+        halves => (halves / 2).to_string(),
+    }
+}
+
+impl Position for Quantum {
+    type Move = Move;
+
+    fn to_move(&self) -> Player {
+        self.player
+    }
+
+    fn legal_moves(&self) -> Vec<Move> {
+        if self.state() != BoardState::ONGOING {
+            return Vec::new();
+        }
+        let collapses: Vec<Option<Cell>> = match self.cycle {
+            Some(mark) => mark.cells.iter().map(|&cell| Some(cell)).collect(),
+            None => vec![None],
+        };
+        let mut moves = Vec::new();
+        for collapse in collapses {
+            let mut next = self.clone();
+            if let (Some(mark), Some(cell)) = (self.cycle, collapse) {
+                next.collapse(mark, cell);
+                next.cycle = None;
+            }
+            if next.state() != BoardState::ONGOING {
+                moves.push(Move {
+                    collapse,
+                    spooky: None,
+                    classical: None,
+                });
+                continue;
+            }
+            let free = next.free_cells();
+            if let [last] = free[..] {
+                moves.push(Move {
+                    collapse,
+                    spooky: None,
+                    classical: Some(last),
+                });
+                continue;
+            }
+            for (idx, &first) in free.iter().enumerate() {
+                for &second in &free[idx + 1..] {
+                    moves.push(Move {
+                        collapse,
+                        spooky: Some((first, second)),
+                        classical: None,
+                    });
+                }
+            }
+        }
+        moves
+    }
+
+    fn play(&mut self, mv: Move) {
+        if let Some(mark) = self.cycle.take() {
+            let cell = mv.collapse.expect("A cycle has to be collapsed first");
+            self.collapse(mark, cell);
+        }
+        if let Some(cells) = mv.spooky {
+            self.place(cells);
+        }
+        if let Some(cell) = mv.classical {
+            self.place_classical(cell);
+        }
+        self.player = self.player.other();
+    }
+
+    /// A line wins, the earlier one if both players got one at once. Without a line the game
+    /// is drawn once the board is full.
+    fn state(&self) -> BoardState {
+        if self.cycle.is_some() {
+            return BoardState::ONGOING;
+        }
+        match self.points() {
+            [one, two] if one > two => BoardState::WON(Player::ONE),
+            [one, two] if two > one => BoardState::WON(Player::TWO),
+            _ if self.board.empty_count() == 0 => BoardState::DRAW,
+            _ => BoardState::ONGOING,
+        }
+    }
+}
+
+impl Playable for Quantum {
+    fn name(&self) -> &'static str {
+        "Quantum Tic-Tac-Toe"
+    }
+
+    fn lines(&self) -> Vec<String> {
+        let names: Vec<Vec<String>> = (0..SIZE)
+            .map(|row| {
+                (0..SIZE)
+                    .map(|col| match (self.board[row][col], self.turns[row][col]) {
+                        (Some(player), Some(turn)) => format!("{}{}", letter(player), turn),
+                        _ => self
+                            .spooky
+                            .iter()
+                            .filter(|mark| mark.cells.contains(&(row, col)))
+                            .map(spooky_name)
+                            .collect::<Vec<String>>()
+                            .join(" "),
+                    })
+                    .collect()
+            })
+            .collect();
+        let width = names
+            .iter()
+            .flatten()
+            .map(|name| name.len())
+            .max()
+            .unwrap_or(0)
+            .max(2)
+            + 2;
+        let mut lines = vec![format!(
+            "  {}",
+            (0..SIZE)
+                .map(|col| format!("{:^width$}", (b'a' + col as u8) as char, width = width))
+                .collect::<Vec<String>>()
+                .join(" ")
+        )];
+        for (row, names) in names.iter().enumerate() {
+            if row > 0 {
+                lines.push(format!("  {}", vec!["-".repeat(width); SIZE].join("+")));
+            }
+            let cells: Vec<String> = names
+                .iter()
+                .map(|name| format!("{:^width$}", name, width = width))
+                .collect();
+            lines.push(format!("{} {}", row + 1, cells.join("|")));
+        }
+        lines.push(String::new());
+        lines.push("X3 is a classical mark of move 3, x3 a spooky one.".to_string());
+        if let Some(mark) = self.cycle {
+            lines.push(format!(
+                "{} closed a cycle: Player {:?} chooses whether it goes to {} or {}.",
+                spooky_name(&mark),
+                self.player,
+                cell_name(mark.cells[0]),
+                cell_name(mark.cells[1])
+            ));
+        }
+        let [one, two] = self.points();
+        if one > 0 && two > 0 {
+            lines.push(format!(
+                "Both players got a line at once. Player ONE scores {}, Player TWO scores {}.",
+                half_points(one),
+                half_points(two)
+            ));
+        }
+        lines
+    }
+
+    fn prompt(&self) -> String {
+        match self.cycle {
+            Some(mark) => format!(
+                "Choose where {} goes, {} or {}, then place your spooky mark, like {} a3 c3. \
+                 The cell alone will do if the collapse ends the game, and the last free cell \
+                 follows it if only one is left",
+                spooky_name(&mark),
+                cell_name(mark.cells[0]),
+                cell_name(mark.cells[1]),
+                cell_name(mark.cells[0])
+            ),
+            None if self.board.empty_count() == 1 => {
+                "Only one cell is left, your mark goes there classically, like c3".to_string()
+            }
+            None => "Place your spooky mark in two cells, like a1 b2".to_string(),
+        }
+    }
+
+    /// `a1 b2` is a spooky mark in a1 and b2, a single cell the classical mark in the last
+    /// free cell. With a cycle to collapse, the cell for its mark comes first: `c3 a1 b2`.
+    fn parse_move(&self, input: &str) -> Result<Move, String> {
+        let cells = input
+            .split_whitespace()
+            .map(|name| match parse_cell(name)? {
+                (row, col) if row < SIZE && col < SIZE => Ok((row, col)),
+                cell => Err(format!(
+                    "'{}' is off the board, use a1 to c3",
+                    cell_name(cell)
+                )),
+            })
+            .collect::<Result<Vec<Cell>, String>>()?;
+        let (collapse, spooky, classical) = match (cells.as_slice(), self.cycle) {
+            ([first, second], None) => (None, Some((*first, *second)), None),
+            ([last], None) if self.board.empty_count() == 1 => (None, None, Some(*last)),
+            ([cell], Some(_)) => (Some(*cell), None, None),
+            ([cell, last], Some(_)) => (Some(*cell), None, Some(*last)),
+            ([cell, first, second], Some(_)) => (Some(*cell), Some((*first, *second)), None),
+            (_, None) => return Err("Enter two cells, like a1 b2".to_string()),
+            (_, Some(_)) => {
+                return Err(
+                    "Enter the cell for the collapse, then two cells, like c3 a1 b2".to_string(),
+                )
+            }
+        };
+        let spooky = match spooky {
+            Some((first, second)) if first == second => {
+                return Err("A spooky mark needs two different cells".to_string())
+            }
+            Some((first, second)) => Some((first.min(second), first.max(second))),
+            None => None,
+        };
+        Ok(Move {
+            collapse,
+            spooky,
+            classical,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn spooky(game: &mut Quantum, first: Cell, second: Cell) {
+        let mv = Move {
+            collapse: None,
+            spooky: Some((first.min(second), first.max(second))),
+            classical: None,
+        };
+        assert!(game.legal_moves().contains(&mv), "{:?}", mv);
+        game.play(mv);
+    }
+
+    #[test]
+    fn closing_a_cycle_hands_the_collapse_to_the_opponent() {
+        let mut game = Quantum::new();
+        assert_eq!(36, game.legal_moves().len());
+        spooky(&mut game, (0, 0), (0, 1));
+        spooky(&mut game, (0, 1), (1, 1));
+        assert_eq!(None, game.cycle);
+        // x3 joins a1 and b2, which o2 and x1 already connect through b1
+        spooky(&mut game, (1, 1), (0, 0));
+        assert_eq!(Player::TWO, game.to_move());
+        assert!(game.cycle.is_some());
+        let moves = game.legal_moves();
+        assert!(moves.iter().all(|mv| mv.collapse.is_some()));
+        // Six free cells are left after either collapse
+        assert_eq!(2 * 15, moves.len());
+    }
+
+    #[test]
+    fn a_collapse_runs_through_the_whole_graph() {
+        let mut game = Quantum::new();
+        spooky(&mut game, (0, 0), (0, 1));
+        spooky(&mut game, (0, 1), (1, 1));
+        // x3 hangs off b2, o4 closes the cycle a1, b1, b2
+        spooky(&mut game, (2, 2), (1, 1));
+        spooky(&mut game, (1, 1), (0, 0));
+        let mv = Move {
+            collapse: Some((1, 1)),
+            spooky: Some(((0, 2), (2, 0))),
+            classical: None,
+        };
+        assert!(game.legal_moves().contains(&mv));
+        game.play(mv);
+        // o4 in b2 forces x3 into c3, o2 into b1 and x1 into a1
+        assert_eq!(Some(Player::TWO), game.board[1][1]);
+        assert_eq!(Some(Player::ONE), game.board[2][2]);
+        assert_eq!(Some(Player::TWO), game.board[0][1]);
+        assert_eq!(Some(Player::ONE), game.board[0][0]);
+        assert_eq!(Some(4), game.turns[1][1]);
+        // Only x5 is left spooky
+        assert_eq!(1, game.spooky.len());
+        assert_eq!(5, game.spooky[0].turn);
+        assert_eq!(None, game.cycle);
+        assert_eq!(Player::TWO, game.to_move());
+    }
+
+    #[test]
+    fn simultaneous_lines_go_to_the_earlier_one() {
+        let mut game = Quantum::new();
+        for (turn, player, cell) in [
+            (1, Player::ONE, (0, 0)),
+            (3, Player::ONE, (0, 1)),
+            (7, Player::ONE, (0, 2)),
+            (2, Player::TWO, (1, 0)),
+            (4, Player::TWO, (1, 1)),
+            (6, Player::TWO, (1, 2)),
+        ] {
+            let _ = game.board.set_value(player, cell);
+            game.turns[cell.0][cell.1] = Some(turn);
+        }
+        // TWO's line was finished by move 6, ONE's by move 7
+        assert_eq!([1, 2], game.points());
+        assert_eq!(BoardState::WON(Player::TWO), game.state());
+        assert!(game.lines().iter().any(|line| line.contains("scores ½")));
+    }
+
+    #[test]
+    fn the_ninth_mark_goes_classical_into_the_last_cell() {
+        let mut game = Quantum::new();
+        // X O X / O X O / O X . with X to move, c3 finishes the diagonal
+        for (turn, player, cell) in [
+            (1, Player::ONE, (0, 0)),
+            (2, Player::TWO, (0, 1)),
+            (3, Player::ONE, (0, 2)),
+            (4, Player::TWO, (1, 0)),
+            (5, Player::ONE, (1, 1)),
+            (6, Player::TWO, (1, 2)),
+            (7, Player::ONE, (2, 1)),
+            (8, Player::TWO, (2, 0)),
+        ] {
+            let _ = game.board.set_value(player, cell);
+            game.turns[cell.0][cell.1] = Some(turn);
+        }
+        game.turn = 9;
+        assert_eq!(BoardState::ONGOING, game.state());
+        let last = Move {
+            collapse: None,
+            spooky: None,
+            classical: Some((2, 2)),
+        };
+        assert_eq!(vec![last], game.legal_moves());
+        assert_eq!(Ok(last), game.parse_move("c3"));
+        game.play(last);
+        assert_eq!(Some(9), game.turns[2][2]);
+        assert_eq!(BoardState::WON(Player::ONE), game.state());
+    }
+
+    #[test]
+    fn moves_can_be_typed() {
+        let mut game = Quantum::new();
+        assert_eq!(
+            Ok(Move {
+                collapse: None,
+                spooky: Some(((0, 0), (1, 1))),
+                classical: None
+            }),
+            game.parse_move("b2 a1")
+        );
+        assert!(game.parse_move("a1 a1").is_err());
+        assert!(game.parse_move("a1").is_err());
+        spooky(&mut game, (0, 0), (0, 1));
+        spooky(&mut game, (0, 0), (0, 1));
+        assert_eq!(
+            Ok(Move {
+                collapse: Some((0, 1)),
+                spooky: Some(((1, 1), (2, 2))),
+                classical: None
+            }),
+            game.parse_move("b1 b2 c3")
+        );
+        assert!(game
+            .lines()
+            .iter()
+            .any(|line| line.contains("closed a cycle")));
+    }
+}
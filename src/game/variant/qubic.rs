@@ -0,0 +1,125 @@
+use crate::game::board::{BoardState, Rules, Shape};
+use crate::game::engine::{Position, Turn};
+use crate::game::player::Player;
+use crate::game::variant::Playable;
+
+const SIZE: usize = 4;
+
+/// Qubic: four in a row on a cube of four layers of 4x4 cells. Lines also run straight
+/// through the layers and diagonally through them, 76 lines in all.
+#[derive(Debug, Clone)]
+pub struct Qubic {
+    turn: Turn,
+}
+
+impl Qubic {
+    pub fn new() -> Self {
+        Qubic {
+            turn: Turn::new(Shape::cube(SIZE), Rules::default()),
+        }
+    }
+}
+
+impl Default for Qubic {
+    fn default() -> Self {
+        Qubic::new()
+    }
+}
+
+/// Plays like the classic game, only on a bigger board.
+impl Position for Qubic {
+    type Move = (usize, usize);
+
+    fn to_move(&self) -> Player {
+        self.turn.to_move()
+    }
+
+    fn legal_moves(&self) -> Vec<Self::Move> {
+        self.turn.legal_moves()
+    }
+
+    fn play(&mut self, mv: Self::Move) {
+        self.turn.play(mv)
+    }
+
+    fn state(&self) -> BoardState {
+        self.turn.state()
+    }
+
+    fn heuristic(&self, player: Player) -> i32 {
+        self.turn.heuristic(player)
+    }
+
+    fn key(&self) -> Option<u64> {
+        self.turn.key()
+    }
+}
+
+impl Playable for Qubic {
+    fn name(&self) -> &'static str {
+        "Qubic"
+    }
+
+    fn lines(&self) -> Vec<String> {
+        self.turn.board.draw_lines()
+    }
+
+    fn prompt(&self) -> String {
+        format!(
+            "Enter the layer, the row and the column, each from 1 to {}, like 2 3 1",
+            SIZE
+        )
+    }
+
+    /// `2 3 1` is the first cell of the third row of the second layer.
+    fn parse_move(&self, input: &str) -> Result<Self::Move, String> {
+        let numbers = input
+            .split_whitespace()
+            .map(|number| match number.parse::<usize>() {
+                Ok(number) if (1..=SIZE).contains(&number) => Ok(number - 1),
+                _ => Err(format!("'{}' is not a number from 1 to {}", number, SIZE)),
+            })
+            .collect::<Result<Vec<usize>, String>>()?;
+        match numbers[..] {
+            [layer, row, col] => Ok(self.turn.board.get_shape().field(layer, row, col)),
+            _ => Err(format!(
+                "'{}' needs three numbers: the layer, the row and the column",
+                input
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::game::engine::{Engine, Strategy};
+
+    #[test]
+    fn moves_name_the_layer_the_row_and_the_column() {
+        let game = Qubic::new();
+        assert_eq!(64, game.legal_moves().len());
+        assert_eq!(Ok((6, 0)), game.parse_move("2 3 1"));
+        assert!(game.parse_move("2 3").is_err());
+        assert!(game.parse_move("5 1 1").is_err());
+    }
+
+    #[test]
+    fn engines_finish_a_line_through_the_layers() {
+        let shape = Shape::cube(SIZE);
+        let mut game = Qubic::new();
+        for (one, two) in [
+            ((0, 0, 3), (0, 0, 0)),
+            ((1, 1, 2), (0, 1, 0)),
+            ((2, 2, 1), (0, 2, 0)),
+        ] {
+            game.play(shape.field(one.0, one.1, one.2));
+            game.play(shape.field(two.0, two.1, two.2));
+        }
+        // ONE has three of the diagonal from the top right of layer 1 down to layer 4,
+        // TWO has three of the first column of layer 1
+        let mut engine = Engine::new(Strategy::MINIMAX(2)).with_seed(1);
+        assert_eq!(Some(shape.field(3, 3, 0)), engine.choose(&game));
+    }
+}
@@ -0,0 +1,328 @@
+use crate::game::board::{Board, BoardState};
+use crate::game::engine::{lines_heuristic, Position};
+use crate::game::notation::{cell_name, parse_cell};
+use crate::game::player::Player;
+use crate::game::variant::{Playable, PLAYABLE_CELLS};
+
+const SIZE: usize = 3;
+
+/// Where a mark goes: the small board, then the cell in it.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Move {
+    pub board: (usize, usize),
+    pub cell: (usize, usize),
+}
+
+/// Ultimate Tic-Tac-Toe. The cell a player takes in a small board sends the opponent to the
+/// small board at the same place of the big one. A won small board becomes a mark on the big
+/// one, three of those in a row win the game.
+#[derive(Debug, Clone)]
+pub struct Ultimate {
+    /// The small boards, row by row.
+    boards: Vec<Board>,
+    /// Holds a mark for every small board that was won.
+    meta: Board,
+    /// The small board the player to move has to play in, any open one if `None`.
+    active: Option<usize>,
+    player: Player,
+}
+
+impl Ultimate {
+    pub fn new() -> Self {
+        Ultimate {
+            boards: vec![Board::default(); SIZE * SIZE],
+            meta: Board::default(),
+            active: None,
+            player: Player::ONE,
+        }
+    }
+
+    /// Whether the small board can still be played in.
+    fn is_open(&self, idx: usize) -> bool {
+        self.boards[idx].get_board_state() == BoardState::ONGOING
+    }
+}
+
+impl Default for Ultimate {
+    fn default() -> Self {
+        Ultimate::new()
+    }
+}
+
+fn board_name(idx: usize) -> String {
+    cell_name((idx / SIZE, idx % SIZE))
+}
+
+impl Position for Ultimate {
+    type Move = Move;
+
+    fn to_move(&self) -> Player {
+        self.player
+    }
+
+    fn legal_moves(&self) -> Vec<Move> {
+        if self.state() != BoardState::ONGOING {
+            return Vec::new();
+        }
+        let boards: Vec<usize> = match self.active {
+            Some(idx) => vec![idx],
+            None => (0..SIZE * SIZE).filter(|&idx| self.is_open(idx)).collect(),
+        };
+        boards
+            .into_iter()
+            .flat_map(|idx| {
+                self.boards[idx].empty_cells().map(move |cell| Move {
+                    board: (idx / SIZE, idx % SIZE),
+                    cell,
+                })
+            })
+            .collect()
+    }
+
+    fn play(&mut self, mv: Move) {
+        let idx = mv.board.0 * SIZE + mv.board.1;
+        self.boards[idx]
+            .set_value(self.player, mv.cell)
+            .expect("Engines only play legal moves");
+        if let BoardState::WON(winner) = self.boards[idx].get_board_state() {
+            self.meta
+                .set_value(winner, mv.board)
+                .expect("A small board is won only once");
+        }
+        let next = mv.cell.0 * SIZE + mv.cell.1;
+        self.active = Some(next).filter(|&next| self.is_open(next));
+        self.player = self.player.other();
+    }
+
+    /// The big board decides, the game is drawn once no small board is left to play in.
+    fn state(&self) -> BoardState {
+        match self.meta.get_board_state() {
+            BoardState::WON(winner) => BoardState::WON(winner),
+            _ if (0..SIZE * SIZE).any(|idx| self.is_open(idx)) => BoardState::ONGOING,
+            _ => BoardState::DRAW,
+        }
+    }
+
+    /// Lines on the big board count far more than lines on the small ones.
+    fn heuristic(&self, player: Player) -> i32 {
+        let small: i32 = (0..SIZE * SIZE)
+            .filter(|&idx| self.is_open(idx))
+            .map(|idx| lines_heuristic(&self.boards[idx], player))
+            .sum();
+        20 * lines_heuristic(&self.meta, player) + small
+    }
+
+    fn key(&self) -> Option<u64> {
+        let boards = self.boards.iter().enumerate().fold(0, |key, (idx, board)| {
+            key ^ board.hash().rotate_left(7 * idx as u32)
+        });
+        let active = self.active.map_or(0, |idx| idx as u64 + 1);
+        let player = self.player.index() as u64;
+        Some(boards ^ (active << 59) ^ (player << 63))
+    }
+}
+
+impl Playable for Ultimate {
+    fn name(&self) -> &'static str {
+        "Ultimate Tic-Tac-Toe"
+    }
+
+    fn lines(&self) -> Vec<String> {
+        let width = 4 * SIZE + SIZE - 1;
+        let mut lines = vec![format!(
+            "  {}",
+            (0..SIZE)
+                .map(|col| format!("{:^width$}", (b'a' + col as u8) as char, width = width))
+                .collect::<Vec<String>>()
+                .join("   ")
+        )];
+        for meta_row in 0..SIZE {
+            if meta_row > 0 {
+                lines.push(format!("  {}", "=".repeat(SIZE * width + 3 * (SIZE - 1))));
+            }
+            for row in 0..SIZE {
+                if row > 0 {
+                    let separator = ["----"; SIZE].join("+");
+                    lines.push(format!("  {}", vec![separator; SIZE].join(" ‖ ")));
+                }
+                let label = if row == SIZE / 2 {
+                    (meta_row + 1).to_string()
+                } else {
+                    " ".to_string()
+                };
+                let boards: Vec<String> = (0..SIZE)
+                    .map(|meta_col| {
+                        let idx = meta_row * SIZE + meta_col;
+                        let playable =
+                            self.is_open(idx) && self.active.is_none_or(|active| active == idx);
+                        self.boards[idx][row]
+                            .iter()
+                            .map(|cell| match cell {
+                                Some(player) => format!(" {} ", player),
+                                None if playable => "  · ".to_string(),
+                                None => "    ".to_string(),
+                            })
+                            .collect::<Vec<String>>()
+                            .join("|")
+                    })
+                    .collect();
+                lines.push(format!("{} {}", label, boards.join(" ‖ ")));
+            }
+        }
+        lines.push(String::new());
+        for (idx, board) in self.boards.iter().enumerate() {
+            match board.get_board_state() {
+                BoardState::WON(winner) => lines.push(format!(
+                    "Board {} was won by Player {:?}",
+                    board_name(idx),
+                    winner
+                )),
+                BoardState::DRAW => lines.push(format!("Board {} is drawn", board_name(idx))),
+                _ => {}
+            }
+        }
+        lines.push(PLAYABLE_CELLS.to_string());
+        lines
+    }
+
+    fn prompt(&self) -> String {
+        match self.active {
+            Some(idx) => format!(
+                "Play in board {}: enter a cell like b2, or the board and the cell like {} b2",
+                board_name(idx),
+                board_name(idx)
+            ),
+            None => "Play in any open board: enter the board and the cell, like a1 b2".to_string(),
+        }
+    }
+
+    /// `a1 b2` is cell b2 of the top left board. The board can be left out when it is forced.
+    fn parse_move(&self, input: &str) -> Result<Move, String> {
+        let names: Vec<&str> = input.split_whitespace().collect();
+        let (board, cell) = match (names.as_slice(), self.active) {
+            ([board, cell], _) => (parse_cell(board)?, parse_cell(cell)?),
+            ([cell], Some(idx)) => ((idx / SIZE, idx % SIZE), parse_cell(cell)?),
+            ([_], None) => return Err("Enter the board and the cell, like a1 b2".to_string()),
+            _ => return Err(format!("'{}' is not a move like a1 b2", input)),
+        };
+        if [board, cell]
+            .iter()
+            .any(|&(row, col)| row >= SIZE || col >= SIZE)
+        {
+            return Err(format!("'{}' is off the board, use a1 to c3", input));
+        }
+        Ok(Move { board, cell })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    /// A small board and a cell in it.
+    type Placement = ((usize, usize), (usize, usize));
+
+    fn play(game: &mut Ultimate, moves: &[Placement]) {
+        for &(board, cell) in moves {
+            let mv = Move { board, cell };
+            assert!(game.legal_moves().contains(&mv), "{:?}", mv);
+            game.play(mv);
+        }
+    }
+
+    #[test]
+    fn cell_sends_the_opponent_to_its_board() {
+        let mut game = Ultimate::new();
+        assert_eq!(81, game.legal_moves().len());
+        play(&mut game, &[((1, 1), (0, 2))]);
+        assert_eq!(Some(2), game.active);
+        let moves = game.legal_moves();
+        assert_eq!(9, moves.len());
+        assert!(moves.iter().all(|mv| mv.board == (0, 2)));
+        assert_eq!(Player::TWO, game.to_move());
+    }
+
+    #[test]
+    fn closed_board_frees_the_choice() {
+        let mut game = Ultimate::new();
+        // ONE takes the top row of board a1, TWO keeps sending ONE back there
+        play(
+            &mut game,
+            &[
+                ((0, 0), (0, 0)),
+                ((0, 0), (1, 1)),
+                ((1, 1), (1, 1)),
+                ((1, 1), (0, 0)),
+                ((0, 0), (0, 1)),
+                ((0, 1), (0, 0)),
+                ((0, 0), (0, 2)),
+            ],
+        );
+        assert_eq!(
+            BoardState::WON(Player::ONE),
+            game.boards[0].get_board_state()
+        );
+        assert_eq!(Some(Player::ONE), game.meta[0][0]);
+        // ONE is sent to a1 again, which is closed, so any open board will do
+        play(&mut game, &[((0, 2), (0, 0))]);
+        assert_eq!(None, game.active);
+        let moves = game.legal_moves();
+        assert!(moves.iter().all(|mv| mv.board != (0, 0)));
+        assert_eq!(81 - 9 - 4, moves.len());
+    }
+
+    #[test]
+    fn three_small_boards_in_a_row_win() {
+        let mut game = Ultimate::new();
+        for idx in 0..SIZE {
+            for col in 0..SIZE {
+                let _ = game.boards[idx].set_value(Player::TWO, (0, col));
+            }
+            let _ = game.meta.set_value(Player::TWO, (0, idx));
+        }
+        assert_eq!(BoardState::WON(Player::TWO), game.state());
+        assert!(game.legal_moves().is_empty());
+    }
+
+    #[test]
+    fn moves_can_be_typed() {
+        let mut game = Ultimate::new();
+        assert_eq!(
+            Ok(Move {
+                board: (0, 0),
+                cell: (1, 1)
+            }),
+            game.parse_move("a1 b2")
+        );
+        assert!(game.parse_move("b2").is_err());
+        assert!(game.parse_move("a1 d4").is_err());
+        game.play(Move {
+            board: (0, 0),
+            cell: (1, 2),
+        });
+        assert_eq!(
+            Ok(Move {
+                board: (1, 2),
+                cell: (2, 0)
+            }),
+            game.parse_move("a3")
+        );
+    }
+
+    #[test]
+    fn drawing_marks_the_active_board() {
+        let mut game = Ultimate::new();
+        game.play(Move {
+            board: (1, 1),
+            cell: (0, 0),
+        });
+        let lines = game.lines();
+        let playable: usize = lines.iter().map(|line| line.matches('·').count()).sum();
+        // The nine cells of board a1, plus the legend
+        assert_eq!(10, playable);
+        assert!(lines
+            .iter()
+            .any(|line| line.contains(&Player::ONE.to_string())));
+    }
+}
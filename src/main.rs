@@ -1,9 +1,129 @@
+#![allow(clippy::upper_case_acronyms, clippy::field_reassign_with_default)]
+
+mod cli;
 mod game;
 
+use cli::Subcommand;
+use game::engine::{Turn, Wild};
+use game::series::Series;
+use game::stats::{self, Store};
+use game::tournament::Tournament;
 use game::Game;
+use std::process;
 
 fn main() {
-    let mut game = Game::new();
+    let args = match cli::parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(2);
+        }
+    };
+
+    let ruleset = ruleset_of(&args);
+    match args.subcommand {
+        Some(Subcommand::STATS(name)) => return game::show_stats_of(&name, &ruleset),
+        Some(Subcommand::LEADERBOARD) => return game::show_leaderboard(&ruleset),
+        Some(Subcommand::RATINGS) => return game::show_ratings(args.rating_system, &ruleset),
+        Some(Subcommand::HISTORY(name)) => {
+            return game::show_rating_history(args.rating_system, &name, &ruleset)
+        }
+        Some(Subcommand::ANALYSE(path)) => return game::show_analysis_of(&path),
+        Some(Subcommand::EVALUATE(position)) => return game::show_evaluation_of(&position),
+        Some(Subcommand::TABLEBASE) => return game::show_tablebase(args.output.as_deref()),
+        Some(Subcommand::BENCH) => return game::bench::run(args.shape, args.threads),
+        Some(Subcommand::TOURNAMENT) => return run_tournament(args),
+        None => {}
+    }
+
+    if let Some(variant) = args.variant {
+        game::variant::play(variant);
+        return;
+    }
+
+    if let Some(best_of) = args.best_of {
+        let mut series = Series::new(best_of)
+            .with_rating_system(args.rating_system)
+            .with_shape(args.shape.unwrap_or_default())
+            .with_rules(args.rules);
+        if let Some(opening) = args.opening {
+            series = series.with_opening(opening);
+        }
+        if let Some(tie_break) = args.tie_break {
+            series = series.with_tie_break(tie_break);
+        }
+        if let Some(control) = args.time_control {
+            series = series.with_time_control(control);
+        }
+        if let Some(names) = args.names {
+            series = series.with_names(names);
+        }
+        series.play();
+        return;
+    }
+
+    let mut game = Game::new()
+        .with_rating_system(args.rating_system)
+        .with_shape(args.shape.unwrap_or_default())
+        .with_rules(args.rules);
+    if let Some(control) = args.time_control {
+        game = game.with_time_control(control);
+    }
+    if let Some(names) = args.names {
+        game = game.with_names(names);
+    }
 
     game.play();
 }
+
+fn run_tournament(args: cli::Args) {
+    if args.engines.len() < 2 {
+        eprintln!("A tournament needs at least two --engines");
+        process::exit(2);
+    }
+    let ruleset = ruleset_of(&args);
+    let mut tournament = Tournament::new(args.engines);
+    if let Some(games) = args.games {
+        tournament = tournament.with_games(games);
+    }
+    if let Some(format) = args.format {
+        tournament = tournament.with_format(format);
+    }
+    if let Some(threads) = args.threads {
+        tournament = tournament.with_threads(threads);
+    }
+    if let Some(seed) = args.seed {
+        tournament = tournament.with_seed(seed);
+    }
+
+    let table = match args.variant {
+        Some(variant) => game::variant::run_tournament(variant, &tournament),
+        None if args.rules.wild => {
+            tournament.run(&Wild::new(args.shape.unwrap_or_default(), args.rules))
+        }
+        None => tournament.run(&Turn::new(args.shape.unwrap_or_default(), args.rules)),
+    };
+    for line in table.lines() {
+        println!("{}", line);
+    }
+
+    if args.record {
+        let recorded = Store::open().and_then(|store| {
+            table
+                .records()
+                .iter()
+                .try_for_each(|record| store.record(&record.clone().with_ruleset(ruleset.clone())))
+        });
+        if let Err(e) = recorded {
+            eprintln!("Couldn't save the games. Err: {}", e);
+        }
+    }
+}
+
+/// The statistics the arguments are about: a variant, or a board with its rules.
+fn ruleset_of(args: &cli::Args) -> String {
+    match args.variant {
+        Some(variant) => variant.to_string(),
+        None => stats::ruleset(args.shape.unwrap_or_default(), args.rules),
+    }
+}